@@ -0,0 +1,171 @@
+//! 对比装箱的`StateMachine`和静态分发的`EnumMachine`：大量角色各自运行一台状态机，每隔几帧切换一次状态。
+//!
+//! 运行：`cargo bench --bench transitions`
+
+use rust4godot::fsm::enum_machine::{EnumMachine, EnumState};
+use rust4godot::fsm::machine::StateMachine;
+use rust4godot::fsm::state::{Context, State, Transition};
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+/// 角色数量
+const ACTORS: usize = 1000;
+/// 计时的帧数
+const FRAMES: usize = 1000;
+const DELTA: f64 = 1.0 / 60.0;
+/// 每个状态停留的帧数
+const FRAMES_PER_STATE: u32 = 4;
+
+/// 一个角色，只有移动需要的数据
+#[derive(Debug, Default)]
+struct Actor {
+    frames: u32,
+    speed: f64,
+    position: f64,
+}
+
+impl Context for Actor {
+    type Input = ();
+    type Event = ();
+}
+
+impl Actor {
+    fn enter(&mut self, speed: f64) {
+        self.frames = 0;
+        self.speed = speed;
+    }
+
+    /// 移动一帧，停留够`FRAMES_PER_STATE`帧时返回`true`
+    fn step(&mut self, delta: f64) -> bool {
+        self.position += self.speed * delta;
+        self.frames += 1;
+        self.frames >= FRAMES_PER_STATE
+    }
+}
+
+#[derive(Debug)]
+struct BoxedIdle;
+
+#[derive(Debug)]
+struct BoxedRun;
+
+impl State<Actor> for BoxedIdle {
+    fn init(&mut self, ctx: &mut Actor) {
+        ctx.enter(0.0);
+    }
+
+    fn process(&mut self, ctx: &mut Actor, delta: f64) -> Transition<Actor> {
+        if ctx.step(delta) {
+            Transition::switch(BoxedRun)
+        } else {
+            Transition::Stay
+        }
+    }
+}
+
+impl State<Actor> for BoxedRun {
+    fn init(&mut self, ctx: &mut Actor) {
+        ctx.enter(1.5);
+    }
+
+    fn process(&mut self, ctx: &mut Actor, delta: f64) -> Transition<Actor> {
+        if ctx.step(delta) {
+            Transition::switch(BoxedIdle)
+        } else {
+            Transition::Stay
+        }
+    }
+}
+
+#[derive(Debug)]
+enum Locomotion {
+    Idle,
+    Run,
+}
+
+impl EnumState<Actor> for Locomotion {
+    fn name(&self) -> &'static str {
+        match self {
+            Locomotion::Idle => "Idle",
+            Locomotion::Run => "Run",
+        }
+    }
+
+    fn init(&mut self, ctx: &mut Actor) {
+        match self {
+            Locomotion::Idle => ctx.enter(0.0),
+            Locomotion::Run => ctx.enter(1.5),
+        }
+    }
+
+    fn process(&mut self, ctx: &mut Actor, delta: f64) -> Option<Self> {
+        if !ctx.step(delta) {
+            return None;
+        }
+        match self {
+            Locomotion::Idle => Some(Locomotion::Run),
+            Locomotion::Run => Some(Locomotion::Idle),
+        }
+    }
+}
+
+/// 预热后运行`FRAMES`帧，返回总耗时
+fn measure(mut frame: impl FnMut()) -> Duration {
+    for _ in 0..FRAMES / 10 {
+        frame();
+    }
+    let start = Instant::now();
+    for _ in 0..FRAMES {
+        frame();
+    }
+    start.elapsed()
+}
+
+fn main() {
+    let mut boxed: Vec<(Actor, StateMachine<Actor>)> = (0..ACTORS)
+        .map(|_| {
+            let mut actor = Actor::default();
+            let mut machine = StateMachine::new();
+            machine.start(&mut actor, Box::new(BoxedIdle));
+            (actor, machine)
+        })
+        .collect();
+    let boxed_time = measure(|| {
+        for (actor, machine) in boxed.iter_mut() {
+            machine.process(actor, DELTA);
+        }
+    });
+
+    let mut enumerated: Vec<(Actor, EnumMachine<Actor, Locomotion>)> = (0..ACTORS)
+        .map(|_| {
+            let mut actor = Actor::default();
+            let mut machine = EnumMachine::new();
+            machine.state(&mut actor, Locomotion::Idle);
+            (actor, machine)
+        })
+        .collect();
+    let enum_time = measure(|| {
+        for (actor, machine) in enumerated.iter_mut() {
+            machine.process(actor, DELTA);
+        }
+    });
+
+    black_box((&boxed, &enumerated));
+    let transitions = ACTORS * FRAMES / FRAMES_PER_STATE as usize;
+    println!(
+        "{} 个角色 × {} 帧（约 {} 次切换）",
+        ACTORS, FRAMES, transitions
+    );
+    for (name, time) in [("StateMachine", boxed_time), ("EnumMachine", enum_time)] {
+        println!(
+            "{:<14}{:>10.2?}  每帧 {:>8.2?}",
+            name,
+            time,
+            time / FRAMES as u32
+        );
+    }
+    println!(
+        "EnumMachine 快 {:.1} 倍",
+        boxed_time.as_secs_f64() / enum_time.as_secs_f64()
+    );
+}
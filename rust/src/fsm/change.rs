@@ -0,0 +1,42 @@
+/// 状态变化的种类
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChangeKind {
+    /// 栈顶状态被替换（包括状态机启动时的第一个状态）
+    Switch,
+    /// 新状态压入栈顶，旧状态暂停
+    Push,
+    /// 栈顶状态弹出，下面的状态恢复
+    Pop,
+    /// 状态机停止，状态退出
+    Stop,
+}
+
+/// 一次活动（栈顶）状态的变化
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StateChange {
+    pub kind: ChangeKind,
+    /// 变化前的活动状态，状态机启动时为`None`
+    pub from: Option<String>,
+    /// 变化后的活动状态，状态机停止时为`None`
+    pub to: Option<String>,
+    /// 引起变化的调用，例如`process`、`event`、`state`
+    pub trigger: &'static str,
+}
+
+impl StateChange {
+    /// 变化前的状态是否被退出（暂停的状态不算退出）
+    pub fn exited(&self) -> Option<&str> {
+        match self.kind {
+            ChangeKind::Push => None,
+            _ => self.from.as_deref(),
+        }
+    }
+
+    /// 变化后的状态是否是新进入的（恢复的状态不算进入）
+    pub fn entered(&self) -> Option<&str> {
+        match self.kind {
+            ChangeKind::Pop | ChangeKind::Stop => None,
+            _ => self.to.as_deref(),
+        }
+    }
+}
@@ -0,0 +1,230 @@
+use crate::fsm::machine::StateMachine;
+use crate::fsm::state::Context;
+use rayon::prelude::*;
+use rayon::{ThreadPool, ThreadPoolBuildError, ThreadPoolBuilder};
+use std::collections::HashMap;
+
+/// 少于这个数量时不分发到工作线程，调度的开销比决策本身更大
+pub const MIN_PARALLEL_ITEMS: usize = 64;
+
+/// 快照中的值
+#[derive(Debug, Clone, PartialEq)]
+pub enum SnapshotValue {
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    Text(String),
+    /// 二维向量（Vector2）
+    Vector(f32, f32),
+}
+
+/// 决策读取的数据快照。
+///
+/// 在主线程中从黑板等Godot对象复制出来，不引用任何Godot对象，因此可以在工作线程中读取
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Snapshot {
+    values: HashMap<String, SnapshotValue>,
+    time_in_state: f64,
+    state_id: Option<String>,
+}
+
+impl Snapshot {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 写入值，替换同名的值
+    pub fn insert(&mut self, key: &str, value: SnapshotValue) {
+        self.values.insert(key.to_string(), value);
+    }
+
+    pub fn get(&self, key: &str) -> Option<&SnapshotValue> {
+        self.values.get(key)
+    }
+
+    pub fn get_bool(&self, key: &str) -> Option<bool> {
+        match self.get(key)? {
+            SnapshotValue::Bool(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    /// 读取数字，整数也会转换为`f64`
+    pub fn get_f64(&self, key: &str) -> Option<f64> {
+        match self.get(key)? {
+            SnapshotValue::Float(value) => Some(*value),
+            SnapshotValue::Int(value) => Some(*value as f64),
+            _ => None,
+        }
+    }
+
+    pub fn get_str(&self, key: &str) -> Option<&str> {
+        match self.get(key)? {
+            SnapshotValue::Text(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    pub fn get_vector(&self, key: &str) -> Option<(f32, f32)> {
+        match self.get(key)? {
+            SnapshotValue::Vector(x, y) => Some((*x, *y)),
+            _ => None,
+        }
+    }
+
+    /// 拍摄快照时当前状态的停留时间（秒）
+    pub fn time_in_state(&self) -> f64 {
+        self.time_in_state
+    }
+
+    pub fn set_time_in_state(&mut self, seconds: f64) {
+        self.time_in_state = seconds;
+    }
+
+    /// 拍摄快照时当前状态的注册ID，决策可以据此只处理自己负责的状态
+    pub fn state_id(&self) -> Option<&str> {
+        self.state_id.as_deref()
+    }
+
+    pub fn set_state_id(&mut self, id: Option<&str>) {
+        self.state_id = id.map(str::to_string);
+    }
+}
+
+/// 可以在工作线程中做出决策的对象：只读取快照，返回要切换到的状态ID
+pub trait Decide: Sync {
+    fn decide(&self, snapshot: &Snapshot) -> Option<String>;
+}
+
+/// 从叶子状态到最外层父状态依次询问`State::decide`，使用第一个结果
+impl<C: Context> Decide for StateMachine<C> {
+    fn decide(&self, snapshot: &Snapshot) -> Option<String> {
+        self.active_states()
+            .rev()
+            .find_map(|state| state.decide(snapshot))
+    }
+}
+
+/// 并行决策的常驻工作线程池：线程在创建时启动，之后每帧复用，不再为每次决策创建线程
+#[derive(Debug)]
+pub struct DecisionPool {
+    pool: ThreadPool,
+    threads: usize,
+}
+
+impl DecisionPool {
+    /// 创建有`threads`个工作线程的线程池，0表示使用可用的核心数
+    pub fn new(threads: usize) -> Result<Self, ThreadPoolBuildError> {
+        let pool = ThreadPoolBuilder::new()
+            .num_threads(threads)
+            .thread_name(|index| format!("decision-{}", index))
+            .build()?;
+        Ok(Self { pool, threads })
+    }
+
+    /// 创建时指定的线程数，0表示使用可用的核心数
+    pub fn threads(&self) -> usize {
+        self.threads
+    }
+
+    /// 对`items`逐个调用`decide`，结果与`items`一一对应。
+    ///
+    /// 在工作线程中并行处理，调用线程等待全部完成；只有一个工作线程或数量少于`MIN_PARALLEL_ITEMS`时直接在当前线程中处理
+    pub fn decide_parallel<T, D>(&self, items: &[T], decide: impl Fn(&T) -> D + Sync) -> Vec<D>
+    where
+        T: Sync,
+        D: Send,
+    {
+        if self.pool.current_num_threads() <= 1 || items.len() < MIN_PARALLEL_ITEMS {
+            return items.iter().map(&decide).collect();
+        }
+        self.pool
+            .install(|| items.par_iter().map(&decide).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fsm::state::State;
+    use std::thread;
+
+    #[derive(Debug, Default)]
+    struct Npc;
+
+    impl Context for Npc {
+        type Input = ();
+        type Event = ();
+    }
+
+    /// 快照中`tired`为真时决定休息
+    #[derive(Debug)]
+    struct Patrol;
+
+    /// 决定逃跑，优先级低于子状态
+    #[derive(Debug)]
+    struct Alert;
+
+    impl State<Npc> for Patrol {
+        fn parent(&self) -> Option<Box<dyn State<Npc>>> {
+            Some(Box::new(Alert))
+        }
+
+        fn decide(&self, snapshot: &Snapshot) -> Option<String> {
+            snapshot
+                .get_bool("tired")
+                .filter(|tired| *tired)
+                .map(|_| "rest".to_string())
+        }
+    }
+
+    impl State<Npc> for Alert {
+        fn decide(&self, _snapshot: &Snapshot) -> Option<String> {
+            Some("flee".to_string())
+        }
+    }
+
+    #[test]
+    fn machines_ask_the_leaf_before_its_parents() {
+        let mut machine = StateMachine::new();
+        machine.start(&mut Npc, Box::new(Patrol));
+
+        let mut snapshot = Snapshot::new();
+        assert_eq!(machine.decide(&snapshot), Some("flee".to_string()));
+        snapshot.insert("tired", SnapshotValue::Bool(true));
+        assert_eq!(machine.decide(&snapshot), Some("rest".to_string()));
+        assert_eq!(StateMachine::<Npc>::new().decide(&snapshot), None);
+    }
+
+    #[test]
+    fn parallel_decisions_keep_the_input_order() {
+        let pool = DecisionPool::new(4).expect("线程池应当创建成功");
+        let items: Vec<usize> = (0..MIN_PARALLEL_ITEMS * 8).collect();
+
+        for _ in 0..3 {
+            let decisions = pool.decide_parallel(&items, |item| {
+                let worker = thread::current().name().map(str::to_string);
+                (item * 2, worker)
+            });
+            let values: Vec<usize> = decisions.iter().map(|(value, _)| *value).collect();
+            assert_eq!(
+                values,
+                items.iter().map(|item| item * 2).collect::<Vec<_>>()
+            );
+            // 决策在线程池的常驻线程中执行
+            assert!(decisions.iter().all(|(_, worker)| {
+                worker
+                    .as_deref()
+                    .is_some_and(|name| name.starts_with("decision-"))
+            }));
+        }
+    }
+
+    #[test]
+    fn small_batches_stay_on_the_calling_thread() {
+        let pool = DecisionPool::new(4).expect("线程池应当创建成功");
+        let caller = thread::current().id();
+        let decisions = pool.decide_parallel(&[1, 2, 3], |item| (*item, thread::current().id()));
+        assert_eq!(decisions, [(1, caller), (2, caller), (3, caller)]);
+    }
+}
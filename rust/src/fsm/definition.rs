@@ -0,0 +1,635 @@
+use crate::fsm::json::{self, Json};
+use crate::fsm::registry::{StateFactory, StateRegistry};
+use crate::fsm::state::Context;
+use crate::fsm::table::{Guard, Rule, TableError, TransitionTable, Trigger};
+use std::fmt;
+use std::sync::Arc;
+
+/// 定义文件中的错误
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DefinitionError {
+    /// 出错的位置，例如`transitions[3].to`，语法错误时为空
+    pub path: String,
+    /// 所在的行，从1开始
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for DefinitionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.path.is_empty() {
+            write!(f, "第{}行：{}", self.line, self.message)
+        } else {
+            write!(f, "第{}行 {}：{}", self.line, self.path, self.message)
+        }
+    }
+}
+
+/// 定义中的一个值及其路径，状态类型和条件通过它读取参数，出错时报告路径和行号
+#[derive(Debug, Clone)]
+pub struct Field<'a> {
+    json: &'a Json,
+    path: String,
+}
+
+impl<'a> Field<'a> {
+    /// 值的路径，例如`states[2].animation`
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    /// 值所在的行
+    pub fn line(&self) -> usize {
+        self.json.line
+    }
+
+    /// 在该值的位置报告错误
+    pub fn error(&self, message: impl Into<String>) -> DefinitionError {
+        DefinitionError {
+            path: self.path.clone(),
+            line: self.json.line,
+            message: message.into(),
+        }
+    }
+
+    /// 对象的字段，不存在时返回`None`
+    pub fn get(&self, key: &str) -> Option<Field<'a>> {
+        self.json.get(key).map(|json| Field {
+            json,
+            path: self.child_path(key),
+        })
+    }
+
+    pub fn as_str(&self) -> Result<&'a str, DefinitionError> {
+        self.json
+            .as_str()
+            .ok_or_else(|| self.error(format!("应为字符串，实际为{}", self.json.type_name())))
+    }
+
+    pub fn as_f64(&self) -> Result<f64, DefinitionError> {
+        self.json
+            .as_f64()
+            .ok_or_else(|| self.error(format!("应为数字，实际为{}", self.json.type_name())))
+    }
+
+    /// 可选的字符串字段，不存在时返回`default`
+    pub fn str_or(&self, key: &str, default: &'a str) -> Result<&'a str, DefinitionError> {
+        self.get(key).map_or(Ok(default), |field| field.as_str())
+    }
+
+    /// 可选的数字字段，不存在时返回`default`
+    pub fn f64_or(&self, key: &str, default: f64) -> Result<f64, DefinitionError> {
+        self.get(key).map_or(Ok(default), |field| field.as_f64())
+    }
+
+    /// 检查对象只包含`allowed`中的字段，用于发现拼写错误
+    pub fn expect_keys(&self, allowed: &[&str]) -> Result<(), DefinitionError> {
+        for (key, field) in self.entries()? {
+            if !allowed.contains(&key) {
+                return Err(field.error(format!(
+                    "未知的字段 {}，可用的字段：{}",
+                    key,
+                    allowed.join(", ")
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// 数组的元素
+    pub fn items(&self) -> Result<Vec<Field<'a>>, DefinitionError> {
+        let items = self
+            .json
+            .as_array()
+            .ok_or_else(|| self.error(format!("应为数组，实际为{}", self.json.type_name())))?;
+        Ok(items
+            .iter()
+            .enumerate()
+            .map(|(index, json)| Field {
+                json,
+                path: format!("{}[{}]", self.path, index),
+            })
+            .collect())
+    }
+
+    /// 对象的所有字段，按声明顺序
+    pub fn entries(&self) -> Result<Vec<(&'a str, Field<'a>)>, DefinitionError> {
+        let fields = self
+            .json
+            .as_object()
+            .ok_or_else(|| self.error(format!("应为对象，实际为{}", self.json.type_name())))?;
+        Ok(fields
+            .iter()
+            .map(|(key, json)| {
+                let field = Field {
+                    json,
+                    path: self.child_path(key),
+                };
+                (key.as_str(), field)
+            })
+            .collect())
+    }
+
+    fn child_path(&self, key: &str) -> String {
+        if self.path.is_empty() {
+            key.to_string()
+        } else {
+            format!("{}.{}", self.path, key)
+        }
+    }
+}
+
+/// 按定义创建状态工厂，参数为状态ID和状态的定义对象。创建的状态应以ID作为`State::name`
+pub type StateKind<C> = fn(&str, &Field<'_>) -> Result<StateFactory<C>, DefinitionError>;
+
+/// 按参数创建守卫条件
+pub type ConditionKind<C> = fn(&Field<'_>) -> Result<Guard<C>, DefinitionError>;
+
+/// 从定义文件构建的状态机
+#[derive(Debug)]
+pub struct MachineDefinition<C: Context> {
+    /// 初始状态ID
+    pub initial: String,
+    pub table: TransitionTable<C>,
+}
+
+/// 定义加载器：把JSON定义文件构建为转换表。
+///
+/// 状态的行为和守卫条件的含义由代码提供（`add_state_kind`/`add_condition`），
+/// 定义文件只负责组合它们：
+///
+/// ```json
+/// {
+///     "initial": "idle",
+///     "states": [
+///         { "id": "idle", "kind": "idle" },
+///         { "id": "run", "kind": "run", "speed_multiplier": 1.5 },
+///         { "id": "pause", "kind": "idle" }
+///     ],
+///     "entries": ["pause"],
+///     "transitions": [
+///         { "on": ["process", "physics_process"], "to": "run", "when": { "moving_towards": "default" } },
+///         { "from": "run", "after": 2.0, "to": "idle", "label": "跑累了" }
+///     ]
+/// }
+/// ```
+///
+/// `from`可以是一个状态ID或ID数组，省略时对任意状态生效；
+/// `on`可以是`process`、`physics_process`、`input`，每个源状态和触发器生成一条规则；
+/// `after`表示停留指定秒数；`when`中的条件全部成立时规则才匹配。
+/// `entries`列出只由代码压入的状态，校验时不会报告它们无法到达。
+pub struct DefinitionLoader<C: Context> {
+    kinds: Vec<(String, StateKind<C>)>,
+    conditions: Vec<(String, ConditionKind<C>)>,
+}
+
+impl<C: Context> Default for DefinitionLoader<C> {
+    fn default() -> Self {
+        Self {
+            kinds: Vec::new(),
+            conditions: Vec::new(),
+        }
+    }
+}
+
+impl<C: Context> fmt::Debug for DefinitionLoader<C> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DefinitionLoader")
+            .field("kinds", &names(&self.kinds))
+            .field("conditions", &names(&self.conditions))
+            .finish()
+    }
+}
+
+impl<C: Context> DefinitionLoader<C> {
+    /// 创建一个没有状态类型和条件的加载器
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 添加状态类型，定义中`kind`为`name`的状态由`kind`创建
+    pub fn add_state_kind(&mut self, name: &str, kind: StateKind<C>) -> &mut Self {
+        self.kinds.push((name.to_string(), kind));
+        self
+    }
+
+    /// 添加守卫条件，定义中`when`的字段名为`name`的条件由`condition`创建
+    pub fn add_condition(&mut self, name: &str, condition: ConditionKind<C>) -> &mut Self {
+        self.conditions.push((name.to_string(), condition));
+        self
+    }
+
+    /// 解析并校验定义文件，返回所有发现的错误
+    pub fn load(&self, source: &str) -> Result<MachineDefinition<C>, Vec<DefinitionError>> {
+        let json = json::parse(source).map_err(|error| {
+            vec![DefinitionError {
+                path: String::new(),
+                line: error.line,
+                message: error.message,
+            }]
+        })?;
+        let root = Field {
+            json: &json,
+            path: String::new(),
+        };
+        root.expect_keys(&["initial", "states", "entries", "transitions"])
+            .map_err(|error| vec![error])?;
+
+        let mut errors = Vec::new();
+        let mut registry = StateRegistry::new();
+        // 已声明的状态ID和它们的位置，状态类型出错时也会记录，避免重复报告
+        let mut declared: Vec<(&str, Field<'_>)> = Vec::new();
+        match root.get("states").map(|states| states.items()) {
+            Some(Ok(states)) => {
+                for state in states {
+                    if let Err(error) = self.load_state(&state, &mut registry, &mut declared) {
+                        errors.push(error);
+                    }
+                }
+            }
+            Some(Err(error)) => errors.push(error),
+            None => errors.push(root.error("缺少states")),
+        }
+        let ids: Vec<&str> = declared.iter().map(|(id, _)| *id).collect();
+
+        let initial = match root.get("initial") {
+            Some(field) => match declared_id(&field, &ids) {
+                Ok(id) => id.to_string(),
+                Err(error) => {
+                    errors.push(error);
+                    String::new()
+                }
+            },
+            None => {
+                errors.push(root.error("缺少initial"));
+                String::new()
+            }
+        };
+
+        let mut table = TransitionTable::new(registry);
+        if let Some(entries) = root.get("entries") {
+            match entries.items() {
+                Ok(entries) => {
+                    for entry in entries {
+                        match declared_id(&entry, &ids) {
+                            Ok(id) => {
+                                table.add_entry(id);
+                            }
+                            Err(error) => errors.push(error),
+                        }
+                    }
+                }
+                Err(error) => errors.push(error),
+            }
+        }
+        if let Some(transitions) = root.get("transitions") {
+            match transitions.items() {
+                Ok(transitions) => {
+                    for transition in transitions {
+                        match self.load_transition(&transition, &ids) {
+                            Ok(rules) => {
+                                for rule in rules {
+                                    table.add(rule);
+                                }
+                            }
+                            Err(error) => errors.push(error),
+                        }
+                    }
+                }
+                Err(error) => errors.push(error),
+            }
+        }
+
+        if !errors.is_empty() {
+            return Err(errors);
+        }
+        if let Err(table_errors) = table.validate(&initial) {
+            for table_error in table_errors {
+                if let TableError::Unreachable(id) = &table_error
+                    && let Some((_, field)) = declared
+                        .iter()
+                        .find(|(declared, _)| *declared == id.as_str())
+                {
+                    errors.push(field.error(table_error.to_string()));
+                } else {
+                    errors.push(root.error(table_error.to_string()));
+                }
+            }
+            return Err(errors);
+        }
+        Ok(MachineDefinition { initial, table })
+    }
+
+    fn load_state<'a>(
+        &self,
+        state: &Field<'a>,
+        registry: &mut StateRegistry<C>,
+        declared: &mut Vec<(&'a str, Field<'a>)>,
+    ) -> Result<(), DefinitionError> {
+        let id_field = state.get("id").ok_or_else(|| state.error("缺少id"))?;
+        let id = id_field.as_str()?;
+        if declared.iter().any(|(declared, _)| *declared == id) {
+            return Err(id_field.error(format!("重复的状态 {}", id)));
+        }
+        declared.push((id, id_field));
+
+        let kind_field = state.get("kind").ok_or_else(|| state.error("缺少kind"))?;
+        let kind = kind_field.as_str()?;
+        let Some((_, state_kind)) = self.kinds.iter().find(|(name, _)| name == kind) else {
+            return Err(kind_field.error(format!(
+                "未知的状态类型 {}，可用的类型：{}",
+                kind,
+                names(&self.kinds).join(", ")
+            )));
+        };
+        registry.register(id, state_kind(id, state)?);
+        Ok(())
+    }
+
+    fn load_transition(
+        &self,
+        transition: &Field<'_>,
+        ids: &[&str],
+    ) -> Result<Vec<Rule<C>>, DefinitionError> {
+        transition.expect_keys(&["from", "to", "on", "after", "when", "label"])?;
+        // `from`可以是一个ID或ID数组，数组中的每个ID生成各自的规则
+        let sources = match transition.get("from") {
+            Some(field) => match field.as_str() {
+                Ok(_) => vec![Some(declared_id(&field, ids)?)],
+                Err(_) => {
+                    let items = field.items()?;
+                    if items.is_empty() {
+                        return Err(field.error("from不能为空"));
+                    }
+                    items
+                        .iter()
+                        .map(|item| declared_id(item, ids).map(Some))
+                        .collect::<Result<_, _>>()?
+                }
+            },
+            None => vec![None],
+        };
+        let to_field = transition
+            .get("to")
+            .ok_or_else(|| transition.error("缺少to"))?;
+        let to = declared_id(&to_field, ids)?;
+        // 触发器不能复制，每个源状态重新读取一次
+        load_triggers::<C>(transition)?;
+
+        let mut guards: Vec<Guard<C>> = Vec::new();
+        let mut labels = Vec::new();
+        if let Some(when) = transition.get("when") {
+            for (name, argument) in when.entries()? {
+                let Some((_, condition)) = self.conditions.iter().find(|(known, _)| known == name)
+                else {
+                    return Err(argument.error(format!(
+                        "未知的条件 {}，可用的条件：{}",
+                        name,
+                        names(&self.conditions).join(", ")
+                    )));
+                };
+                guards.push(condition(&argument)?);
+                labels.push(match argument.json.as_str() {
+                    Some(value) => format!("{}={}", name, value),
+                    None => name.to_string(),
+                });
+            }
+        }
+        // 没有条件时label仍然保留在规则上，用于调试和导出
+        let label = match transition.get("label") {
+            Some(field) => Some(field.as_str()?.to_string()),
+            None => (!labels.is_empty()).then(|| labels.join(" && ")),
+        };
+
+        let guards = Arc::new(guards);
+        let mut rules = Vec::new();
+        for from in sources {
+            for trigger in load_triggers(transition)? {
+                let rule = Rule::new(from, trigger, to);
+                rules.push(match &label {
+                    Some(label) if !guards.is_empty() => {
+                        let guards = guards.clone();
+                        rule.when(label, move |ctx| guards.iter().all(|guard| guard(ctx)))
+                    }
+                    Some(label) => rule.labeled(label),
+                    None => rule,
+                });
+            }
+        }
+        Ok(rules)
+    }
+}
+
+/// 读取规则的触发器：`on`中的每个回调和`after`各生成一个
+fn load_triggers<C: Context>(transition: &Field<'_>) -> Result<Vec<Trigger<C>>, DefinitionError> {
+    let mut triggers = Vec::new();
+    if let Some(on) = transition.get("on") {
+        let names = match on.as_str() {
+            Ok(_) => vec![on],
+            Err(_) => on.items()?,
+        };
+        for name in names {
+            triggers.push(match name.as_str()? {
+                "process" => Trigger::Process,
+                "physics_process" => Trigger::PhysicsProcess,
+                "input" => Trigger::Input,
+                other => {
+                    return Err(name.error(format!(
+                        "未知的触发器 {}，可用的触发器：process, physics_process, input",
+                        other
+                    )));
+                }
+            });
+        }
+    }
+    if let Some(after) = transition.get("after") {
+        let seconds = after.as_f64()?;
+        if seconds < 0.0 {
+            return Err(after.error("停留时间不能为负数"));
+        }
+        triggers.push(Trigger::After(seconds));
+    }
+    if triggers.is_empty() {
+        return Err(transition.error("缺少on或after"));
+    }
+    Ok(triggers)
+}
+
+/// 读取状态ID，并检查它已在`states`中声明
+fn declared_id<'a>(field: &Field<'a>, ids: &[&str]) -> Result<&'a str, DefinitionError> {
+    let id = field.as_str()?;
+    if ids.contains(&id) {
+        Ok(id)
+    } else {
+        Err(field.error(format!("状态 {} 未定义", id)))
+    }
+}
+
+fn names<T>(entries: &[(String, T)]) -> Vec<&str> {
+    entries.iter().map(|(name, _)| name.as_str()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fsm::state::State;
+    use crate::fsm::table::Fired;
+
+    #[derive(Debug, Default)]
+    struct Ctx {
+        moving: bool,
+    }
+
+    impl Context for Ctx {
+        type Input = ();
+        type Event = ();
+    }
+
+    #[derive(Debug)]
+    struct Named(String);
+
+    impl State<Ctx> for Named {
+        fn name(&self) -> &str {
+            &self.0
+        }
+    }
+
+    fn plain(id: &str, _params: &Field<'_>) -> Result<StateFactory<Ctx>, DefinitionError> {
+        let id = id.to_string();
+        Ok(Box::new(move || Box::new(Named(id.clone()))))
+    }
+
+    fn moving(_argument: &Field<'_>) -> Result<Guard<Ctx>, DefinitionError> {
+        Ok(Box::new(|ctx: &Ctx| ctx.moving))
+    }
+
+    fn loader() -> DefinitionLoader<Ctx> {
+        let mut loader = DefinitionLoader::new();
+        loader
+            .add_state_kind("plain", plain)
+            .add_condition("moving", moving);
+        loader
+    }
+
+    /// 加载失败时的错误：行号、路径和信息
+    fn errors(source: &str) -> Vec<(usize, String, String)> {
+        loader()
+            .load(source)
+            .expect_err("定义应当有错误")
+            .into_iter()
+            .map(|error| (error.line, error.path, error.message))
+            .collect()
+    }
+
+    fn error(line: usize, path: &str, message: &str) -> (usize, String, String) {
+        (line, path.to_string(), message.to_string())
+    }
+
+    #[test]
+    fn loads_states_entries_and_rules() {
+        let definition = loader()
+            .load(
+                r#"{
+    "initial": "idle",
+    "states": [
+        { "id": "idle", "kind": "plain" },
+        { "id": "run", "kind": "plain" },
+        { "id": "pause", "kind": "plain" }
+    ],
+    "entries": ["pause"],
+    "transitions": [
+        { "on": ["process", "input"], "to": "run", "when": { "moving": true } },
+        { "from": "run", "after": 2.0, "to": "idle", "label": "跑累了" }
+    ]
+}"#,
+            )
+            .expect("定义应当有效");
+
+        assert_eq!(definition.initial, "idle");
+        let table = &definition.table;
+        assert_eq!(table.registry().ids(), ["idle", "run", "pause"]);
+        assert_eq!(table.entries(), ["pause"]);
+        assert_eq!(table.rules().len(), 3);
+        assert_eq!(table.rules()[0].label(), Some("moving"));
+        // 没有条件的规则也保留label
+        assert_eq!(table.rules()[2].label(), Some("跑累了"));
+
+        let moving = Ctx { moving: true };
+        assert_eq!(
+            table.evaluate(&moving, Some("idle"), &Fired::Process),
+            Some("run")
+        );
+        assert_eq!(
+            table.evaluate(&Ctx::default(), Some("idle"), &Fired::Process),
+            None
+        );
+    }
+
+    #[test]
+    fn reports_syntax_errors_with_their_line() {
+        assert_eq!(
+            errors("{\n    \"initial\": 01\n}"),
+            [error(2, "", "无效的数字 01")]
+        );
+    }
+
+    #[test]
+    fn reports_unknown_kinds_and_duplicate_ids() {
+        let source = r#"{
+    "initial": "idle",
+    "states": [
+        { "id": "idle", "kind": "plain" },
+        { "id": "run", "kind": "sprint" },
+        { "id": "idle", "kind": "plain" }
+    ],
+    "transitions": []
+}"#;
+        assert_eq!(
+            errors(source),
+            [
+                error(
+                    5,
+                    "states[1].kind",
+                    "未知的状态类型 sprint，可用的类型：plain"
+                ),
+                error(6, "states[2].id", "重复的状态 idle"),
+            ]
+        );
+    }
+
+    #[test]
+    fn reports_undefined_targets() {
+        let source = r#"{
+    "initial": "idle",
+    "states": [
+        { "id": "idle", "kind": "plain" }
+    ],
+    "transitions": [
+        { "on": "process", "to": "fly" }
+    ]
+}"#;
+        assert_eq!(
+            errors(source),
+            [error(7, "transitions[0].to", "状态 fly 未定义")]
+        );
+    }
+
+    #[test]
+    fn reports_unreachable_states_at_their_declaration() {
+        let source = r#"{
+    "initial": "idle",
+    "states": [
+        { "id": "idle", "kind": "plain" },
+        { "id": "run", "kind": "plain" },
+        { "id": "pause", "kind": "plain" }
+    ],
+    "transitions": [
+        { "from": "idle", "on": "process", "to": "run" }
+    ]
+}"#;
+        assert_eq!(
+            errors(source),
+            [error(6, "states[2].id", "状态 pause 无法从初始状态到达")]
+        );
+    }
+}
@@ -0,0 +1,297 @@
+use crate::fsm::state::Context;
+use std::fmt;
+use std::marker::PhantomData;
+
+/// 静态分发的状态，通常由一个封闭的枚举实现，每个变体是一个状态（变体的字段就是状态自己的数据）。
+///
+/// 回调返回`Some(next)`时切换到`next`，返回`None`时保持当前状态。
+/// 新状态按值返回并直接保存在状态机里，切换不需要堆分配，回调也不经过虚表。
+pub trait EnumState<C: Context>: Sized {
+    /// 状态名称，用于调试
+    fn name(&self) -> &'static str;
+
+    /// 进入该状态时调用
+    fn init(&mut self, _ctx: &mut C) {}
+
+    /// 离开该状态时调用
+    fn exit(&mut self, _ctx: &mut C) {}
+
+    /// 对应`_input()`回调
+    fn input(&mut self, _ctx: &mut C, _event: &C::Input) -> Option<Self> {
+        None
+    }
+
+    /// 处理自定义事件
+    fn event(&mut self, _ctx: &mut C, _event: &C::Event) -> Option<Self> {
+        None
+    }
+
+    /// 对应`_process()`回调
+    fn process(&mut self, _ctx: &mut C, _delta: f64) -> Option<Self> {
+        None
+    }
+
+    /// 对应`_physics_process()`回调
+    fn physics_process(&mut self, _ctx: &mut C, _delta: f64) -> Option<Self> {
+        None
+    }
+}
+
+/// 静态分发的状态机：`StateMachine`的轻量替代，适合同时运行成百上千台的NPC。
+///
+/// 状态集合在编译期确定，不支持分层状态、状态栈、转换表和变化记录；
+/// 每个回调都是一次直接调用，切换状态时不分配内存。
+/// 与`StateMachine`一样记录当前状态的停留时间，并在回调前写入上下文。
+pub struct EnumMachine<C: Context, S: EnumState<C>> {
+    state: Option<S>,
+    elapsed: f64,
+    changed: bool,
+    context: PhantomData<fn(&mut C)>,
+}
+
+impl<C: Context, S: EnumState<C>> Default for EnumMachine<C, S> {
+    fn default() -> Self {
+        Self {
+            state: None,
+            elapsed: 0.0,
+            changed: false,
+            context: PhantomData,
+        }
+    }
+}
+
+impl<C: Context, S: EnumState<C> + fmt::Debug> fmt::Debug for EnumMachine<C, S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("EnumMachine")
+            .field("state", &self.state)
+            .field("elapsed", &self.elapsed)
+            .finish()
+    }
+}
+
+impl<C: Context, S: EnumState<C>> EnumMachine<C, S> {
+    /// 创建一台没有活动状态的状态机
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 当前状态
+    pub fn current_state(&self) -> Option<&S> {
+        self.state.as_ref()
+    }
+
+    /// 当前状态的名称
+    pub fn current_state_name(&self) -> Option<&'static str> {
+        self.state.as_ref().map(|state| state.name())
+    }
+
+    /// 当前状态的停留时间（秒）
+    pub fn time_in_state(&self) -> f64 {
+        self.elapsed
+    }
+
+    /// 最近一次调用是否切换了状态
+    pub fn changed(&self) -> bool {
+        self.changed
+    }
+
+    /// 退出当前状态（如果有），进入`state`
+    pub fn state(&mut self, ctx: &mut C, state: S) {
+        self.changed = false;
+        self.switch(ctx, state);
+    }
+
+    /// 退出当前状态，状态机回到没有活动状态
+    pub fn stop(&mut self, ctx: &mut C) {
+        self.changed = false;
+        if let Some(mut state) = self.state.take() {
+            ctx.set_time_in_state(self.elapsed);
+            state.exit(ctx);
+            self.elapsed = 0.0;
+            self.changed = true;
+        }
+    }
+
+    /// 分发输入事件
+    pub fn input(&mut self, ctx: &mut C, event: &C::Input) {
+        self.dispatch(ctx, |state, ctx| state.input(ctx, event));
+    }
+
+    /// 分发自定义事件
+    pub fn send_event(&mut self, ctx: &mut C, event: &C::Event) {
+        self.dispatch(ctx, |state, ctx| state.event(ctx, event));
+    }
+
+    /// 分发更新事件，并累加当前状态的停留时间
+    pub fn process(&mut self, ctx: &mut C, delta: f64) {
+        if self.state.is_some() {
+            self.elapsed += delta;
+        }
+        self.dispatch(ctx, |state, ctx| state.process(ctx, delta));
+    }
+
+    /// 分发物理更新事件
+    pub fn physics_process(&mut self, ctx: &mut C, delta: f64) {
+        self.dispatch(ctx, |state, ctx| state.physics_process(ctx, delta));
+    }
+
+    fn dispatch(&mut self, ctx: &mut C, callback: impl FnOnce(&mut S, &mut C) -> Option<S>) {
+        self.changed = false;
+        let Some(state) = &mut self.state else {
+            return;
+        };
+        ctx.set_time_in_state(self.elapsed);
+        if let Some(next) = callback(state, ctx) {
+            self.switch(ctx, next);
+        }
+    }
+
+    fn switch(&mut self, ctx: &mut C, mut next: S) {
+        if let Some(mut state) = self.state.take() {
+            ctx.set_time_in_state(self.elapsed);
+            state.exit(ctx);
+        }
+        self.elapsed = 0.0;
+        ctx.set_time_in_state(0.0);
+        next.init(ctx);
+        self.state = Some(next);
+        self.changed = true;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 记录回调顺序和回调时停留时间的上下文
+    #[derive(Debug, Default)]
+    struct Log {
+        calls: Vec<String>,
+        time_in_state: f64,
+    }
+
+    impl Log {
+        fn take(&mut self) -> Vec<String> {
+            std::mem::take(&mut self.calls)
+        }
+    }
+
+    impl Context for Log {
+        type Input = ();
+        type Event = &'static str;
+
+        fn time_in_state(&self) -> f64 {
+            self.time_in_state
+        }
+
+        fn set_time_in_state(&mut self, seconds: f64) {
+            self.time_in_state = seconds;
+        }
+    }
+
+    #[derive(Debug, PartialEq)]
+    enum Door {
+        Closed,
+        /// 打开后经过的帧数
+        Open {
+            frames: u32,
+        },
+    }
+
+    impl EnumState<Log> for Door {
+        fn name(&self) -> &'static str {
+            match self {
+                Door::Closed => "Closed",
+                Door::Open { .. } => "Open",
+            }
+        }
+
+        fn init(&mut self, ctx: &mut Log) {
+            let call = format!("{}.init@{}", self.name(), ctx.time_in_state);
+            ctx.calls.push(call);
+        }
+
+        fn exit(&mut self, ctx: &mut Log) {
+            let call = format!("{}.exit@{}", self.name(), ctx.time_in_state);
+            ctx.calls.push(call);
+        }
+
+        fn event(&mut self, _ctx: &mut Log, event: &&'static str) -> Option<Self> {
+            match (&*self, *event) {
+                (Door::Closed, "open") | (Door::Open { .. }, "reopen") => {
+                    Some(Door::Open { frames: 0 })
+                }
+                (Door::Open { .. }, "close") => Some(Door::Closed),
+                _ => None,
+            }
+        }
+
+        fn process(&mut self, _ctx: &mut Log, _delta: f64) -> Option<Self> {
+            if let Door::Open { frames } = self {
+                *frames += 1;
+            }
+            None
+        }
+    }
+
+    #[test]
+    fn switching_exits_the_old_state_before_entering_the_new_one() {
+        let mut machine = EnumMachine::new();
+        let mut log = Log::default();
+        machine.state(&mut log, Door::Closed);
+        assert_eq!(log.take(), ["Closed.init@0"]);
+        assert!(machine.changed());
+
+        machine.process(&mut log, 0.5);
+        assert!(!machine.changed());
+        machine.send_event(&mut log, &"open");
+        // 旧状态退出时看到自己的停留时间，新状态从0开始
+        assert_eq!(log.take(), ["Closed.exit@0.5", "Open.init@0"]);
+        assert!(machine.changed());
+        assert_eq!(machine.current_state_name(), Some("Open"));
+        assert_eq!(machine.time_in_state(), 0.0);
+
+        machine.send_event(&mut log, &"ignored");
+        assert!(log.take().is_empty());
+        assert!(!machine.changed());
+    }
+
+    #[test]
+    fn self_transition_reenters_with_fresh_data() {
+        let mut machine = EnumMachine::new();
+        let mut log = Log::default();
+        machine.state(&mut log, Door::Open { frames: 0 });
+        machine.process(&mut log, 0.25);
+        machine.process(&mut log, 0.25);
+        assert_eq!(machine.current_state(), Some(&Door::Open { frames: 2 }));
+        log.take();
+
+        machine.send_event(&mut log, &"reopen");
+        assert_eq!(log.take(), ["Open.exit@0.5", "Open.init@0"]);
+        assert!(machine.changed());
+        assert_eq!(machine.current_state(), Some(&Door::Open { frames: 0 }));
+        assert_eq!(machine.time_in_state(), 0.0);
+    }
+
+    #[test]
+    fn stop_exits_once_and_ignores_later_callbacks() {
+        let mut machine = EnumMachine::new();
+        let mut log = Log::default();
+        machine.state(&mut log, Door::Closed);
+        machine.process(&mut log, 1.0);
+        log.take();
+
+        machine.stop(&mut log);
+        assert_eq!(log.take(), ["Closed.exit@1"]);
+        assert!(machine.changed());
+        assert_eq!(machine.current_state(), None);
+        assert_eq!(machine.time_in_state(), 0.0);
+
+        machine.stop(&mut log);
+        machine.process(&mut log, 1.0);
+        machine.send_event(&mut log, &"open");
+        assert!(log.take().is_empty());
+        assert!(!machine.changed());
+        assert_eq!(machine.time_in_state(), 0.0);
+    }
+}
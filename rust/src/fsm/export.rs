@@ -0,0 +1,412 @@
+use crate::fsm::state::Context;
+use crate::fsm::table::TransitionTable;
+use std::fmt::Write;
+
+/// 图中的状态节点
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GraphNode {
+    /// 注册ID
+    pub id: String,
+    /// 父状态名称（最外层父状态在前）
+    pub parents: Vec<String>,
+}
+
+/// 图中的一条边，同一对状态之间的多条规则合并为一条
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GraphEdge {
+    pub from: String,
+    pub to: String,
+    /// 各规则的标签（守卫条件和触发回调）
+    pub labels: Vec<String>,
+}
+
+/// 从转换表提取的状态图，可以导出为Graphviz DOT或Mermaid文本
+#[derive(Debug, Clone, Default)]
+pub struct StateGraph {
+    pub name: String,
+    pub initial: Option<String>,
+    pub nodes: Vec<GraphNode>,
+    pub edges: Vec<GraphEdge>,
+}
+
+impl StateGraph {
+    /// 遍历转换表中注册的状态和规则。对任意状态生效的规则会展开为每个状态出发的边
+    pub fn from_table<C: Context>(
+        name: &str,
+        table: &TransitionTable<C>,
+        initial: Option<&str>,
+    ) -> Self {
+        let registry = table.registry();
+        let nodes: Vec<GraphNode> = registry
+            .ids()
+            .into_iter()
+            .map(|id| {
+                let mut parents = Vec::new();
+                if let Some(state) = registry.create(id) {
+                    let mut current = state.parent();
+                    while let Some(parent) = current {
+                        parents.insert(0, parent.name().to_string());
+                        current = parent.parent();
+                    }
+                }
+                GraphNode {
+                    id: id.to_string(),
+                    parents,
+                }
+            })
+            .collect();
+
+        let mut graph = Self {
+            name: name.to_string(),
+            initial: initial.map(str::to_string),
+            nodes,
+            edges: Vec::new(),
+        };
+
+        for rule in table.rules() {
+            let label = match rule.label() {
+                Some(guard) => format!("{} [{}]", guard, rule.trigger().name()),
+                None => format!("[{}]", rule.trigger().name()),
+            };
+            let sources: Vec<String> = match rule.from() {
+                Some(from) => vec![from.to_string()],
+                None => graph.nodes.iter().map(|node| node.id.clone()).collect(),
+            };
+            for from in sources {
+                // 目标就是当前状态的规则不会引起切换
+                if from != rule.to() {
+                    graph.add_edge(&from, rule.to(), label.clone());
+                }
+            }
+        }
+
+        graph
+    }
+
+    /// 导出为Graphviz DOT文本，父状态导出为子图
+    pub fn to_dot(&self) -> String {
+        let mut out = String::new();
+        let _ = writeln!(out, "digraph {} {{", quote(&self.name));
+        let _ = writeln!(out, "    rankdir=LR;");
+        let _ = writeln!(out, "    node [shape=box, style=rounded];");
+
+        if let Some(initial) = &self.initial {
+            let _ = writeln!(out, "    __start [shape=point];");
+            let _ = writeln!(out, "    __start -> {};", quote(initial));
+        }
+
+        for (parent, nodes) in self.groups() {
+            match parent {
+                Some(parent) => {
+                    let _ = writeln!(
+                        out,
+                        "    subgraph {} {{",
+                        quote(&format!("cluster_{}", parent))
+                    );
+                    let _ = writeln!(out, "        label={};", quote(parent));
+                    for node in nodes {
+                        let _ = writeln!(out, "        {};", quote(&node.id));
+                    }
+                    let _ = writeln!(out, "    }}");
+                }
+                None => {
+                    for node in nodes {
+                        let _ = writeln!(out, "    {};", quote(&node.id));
+                    }
+                }
+            }
+        }
+
+        for edge in &self.edges {
+            let labels: Vec<String> = edge.labels.iter().map(|label| escape(label)).collect();
+            let _ = writeln!(
+                out,
+                "    {} -> {} [label=\"{}\"];",
+                quote(&edge.from),
+                quote(&edge.to),
+                labels.join("\\n"),
+            );
+        }
+
+        out.push_str("}\n");
+        out
+    }
+
+    /// 导出为Mermaid stateDiagram-v2文本，父状态导出为复合状态。
+    ///
+    /// 只含字母、数字和下划线的ID直接作为标识符，其他ID使用生成的别名，并以`state "ID" as 别名`声明显示名称；
+    /// 名称和标签中的特殊字符写为Mermaid的实体编码（例如`:`写为`#58;`）
+    pub fn to_mermaid(&self) -> String {
+        let ids = MermaidIds::new(self);
+        let mut out = String::new();
+        let _ = writeln!(out, "---");
+        let _ = writeln!(out, "title: \"{}\"", escape(&self.name));
+        let _ = writeln!(out, "---");
+        let _ = writeln!(out, "stateDiagram-v2");
+
+        for (name, alias) in &ids.aliases {
+            let _ = writeln!(out, "    state \"{}\" as {}", mermaid_text(name), alias);
+        }
+
+        if let Some(initial) = &self.initial {
+            let _ = writeln!(out, "    [*] --> {}", ids.get(initial));
+        }
+
+        for (parent, nodes) in self.groups() {
+            match parent {
+                Some(parent) => {
+                    let _ = writeln!(out, "    state {} {{", ids.get(parent));
+                    for node in nodes {
+                        let _ = writeln!(out, "        {}", ids.get(&node.id));
+                    }
+                    let _ = writeln!(out, "    }}");
+                }
+                None => {
+                    for node in nodes {
+                        let _ = writeln!(out, "    {}", ids.get(&node.id));
+                    }
+                }
+            }
+        }
+
+        for edge in &self.edges {
+            let labels: Vec<String> = edge
+                .labels
+                .iter()
+                .map(|label| mermaid_text(label))
+                .collect();
+            let _ = writeln!(
+                out,
+                "    {} --> {}: {}",
+                ids.get(&edge.from),
+                ids.get(&edge.to),
+                labels.join("<br/>")
+            );
+        }
+
+        out
+    }
+
+    // 合并同一对状态之间的边
+    fn add_edge(&mut self, from: &str, to: &str, label: String) {
+        match self
+            .edges
+            .iter_mut()
+            .find(|edge| edge.from == from && edge.to == to)
+        {
+            Some(edge) => {
+                if !edge.labels.contains(&label) {
+                    edge.labels.push(label);
+                }
+            }
+            None => self.edges.push(GraphEdge {
+                from: from.to_string(),
+                to: to.to_string(),
+                labels: vec![label],
+            }),
+        }
+    }
+
+    // 按直接父状态分组节点，保持注册顺序
+    fn groups(&self) -> Vec<(Option<&str>, Vec<&GraphNode>)> {
+        let mut groups: Vec<(Option<&str>, Vec<&GraphNode>)> = Vec::new();
+        for node in &self.nodes {
+            let parent = node.parents.last().map(String::as_str);
+            match groups.iter_mut().find(|(key, _)| *key == parent) {
+                Some((_, nodes)) => nodes.push(node),
+                None => groups.push((parent, vec![node])),
+            }
+        }
+        groups
+    }
+}
+
+// DOT中的带引号标识符
+fn quote(text: &str) -> String {
+    format!("\"{}\"", escape(text))
+}
+
+// DOT带引号字符串的转义：反斜杠、引号和换行（与YAML的双引号字符串相同）
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+// Mermaid文本中的特殊字符写为实体编码，换行写为<br/>
+fn mermaid_text(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '#' => out.push_str("#35;"),
+            '"' => out.push_str("#quot;"),
+            ':' => out.push_str("#58;"),
+            ';' => out.push_str("#59;"),
+            '<' => out.push_str("#lt;"),
+            '>' => out.push_str("#gt;"),
+            '\n' => out.push_str("<br/>"),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+/// Mermaid的关键字不能直接作为状态标识符
+const MERMAID_KEYWORDS: [&str; 7] = [
+    "state",
+    "note",
+    "end",
+    "as",
+    "direction",
+    "class",
+    "classDef",
+];
+
+/// 需要别名的状态名称（状态ID和父状态名称）及其别名
+struct MermaidIds {
+    aliases: Vec<(String, String)>,
+}
+
+impl MermaidIds {
+    fn new(graph: &StateGraph) -> Self {
+        let names = graph
+            .nodes
+            .iter()
+            .flat_map(|node| node.parents.iter().chain([&node.id]))
+            .chain(graph.initial.iter())
+            .chain(graph.edges.iter().flat_map(|edge| [&edge.from, &edge.to]));
+        let mut ids = Self {
+            aliases: Vec::new(),
+        };
+        let mut next = 0;
+        for name in names {
+            if is_plain_id(name) || ids.aliases.iter().any(|(known, _)| known == name) {
+                continue;
+            }
+            // 别名不能与直接使用的ID重复
+            let alias = loop {
+                let alias = format!("s{}", next);
+                next += 1;
+                if !graph
+                    .nodes
+                    .iter()
+                    .any(|node| node.id == alias || node.parents.contains(&alias))
+                {
+                    break alias;
+                }
+            };
+            ids.aliases.push((name.clone(), alias));
+        }
+        ids
+    }
+
+    /// 状态在Mermaid中的标识符
+    fn get<'a>(&'a self, name: &'a str) -> &'a str {
+        self.aliases
+            .iter()
+            .find(|(known, _)| known == name)
+            .map_or(name, |(_, alias)| alias)
+    }
+}
+
+// 只含ASCII字母、数字和下划线且不是关键字
+fn is_plain_id(name: &str) -> bool {
+    !name.is_empty()
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+        && !MERMAID_KEYWORDS.contains(&name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn node(id: &str, parents: &[&str]) -> GraphNode {
+        GraphNode {
+            id: id.to_string(),
+            parents: parents.iter().map(|parent| parent.to_string()).collect(),
+        }
+    }
+
+    fn edge(from: &str, to: &str, labels: &[&str]) -> GraphEdge {
+        GraphEdge {
+            from: from.to_string(),
+            to: to.to_string(),
+            labels: labels.iter().map(|label| label.to_string()).collect(),
+        }
+    }
+
+    /// 含有冒号、空格、引号和关键字的状态图
+    fn graph() -> StateGraph {
+        StateGraph {
+            name: "Boat \"A\"".to_string(),
+            initial: Some("idle".to_string()),
+            nodes: vec![
+                node("idle", &["Ground"]),
+                node("dock: left", &["Ground"]),
+                node("end", &[]),
+            ],
+            edges: vec![
+                edge("idle", "dock: left", &["near: dock [process]", "[after]"]),
+                edge("dock: left", "end", &["[event]"]),
+            ],
+        }
+    }
+
+    #[test]
+    fn dot_quotes_ids_and_labels() {
+        let expected = r#"digraph "Boat \"A\"" {
+    rankdir=LR;
+    node [shape=box, style=rounded];
+    __start [shape=point];
+    __start -> "idle";
+    subgraph "cluster_Ground" {
+        label="Ground";
+        "idle";
+        "dock: left";
+    }
+    "end";
+    "idle" -> "dock: left" [label="near: dock [process]\n[after]"];
+    "dock: left" -> "end" [label="[event]"];
+}
+"#;
+        assert_eq!(graph().to_dot(), expected);
+        assert_eq!(quote("a\\b\"c\nd"), r#""a\\b\"c\nd""#);
+    }
+
+    #[test]
+    fn mermaid_aliases_special_ids_and_encodes_labels() {
+        let expected = r#"---
+title: "Boat \"A\""
+---
+stateDiagram-v2
+    state "dock#58; left" as s0
+    state "end" as s1
+    [*] --> idle
+    state Ground {
+        idle
+        s0
+    }
+    s1
+    idle --> s0: near#58; dock [process]<br/>[after]
+    s0 --> s1: [event]
+"#;
+        assert_eq!(graph().to_mermaid(), expected);
+        assert_eq!(
+            mermaid_text("a#b;\"c\"<d>"),
+            "a#35;b#59;#quot;c#quot;#lt;d#gt;"
+        );
+    }
+
+    #[test]
+    fn mermaid_aliases_do_not_collide_with_plain_ids() {
+        let graph = StateGraph {
+            name: "g".to_string(),
+            initial: None,
+            nodes: vec![node("s0", &[]), node("run fast", &[])],
+            edges: vec![edge("s0", "run fast", &["[process]"])],
+        };
+        let mermaid = graph.to_mermaid();
+        assert!(mermaid.contains("    state \"run fast\" as s1\n"));
+        assert!(mermaid.contains("    s0 --> s1: [process]\n"));
+    }
+}
@@ -0,0 +1,189 @@
+use crate::fsm::change::{ChangeKind, StateChange};
+use std::collections::VecDeque;
+use std::fmt;
+
+/// 一条转换历史记录
+#[derive(Debug, Clone, PartialEq)]
+pub struct HistoryEntry {
+    /// 所在区域
+    pub region: String,
+    pub kind: ChangeKind,
+    pub from: Option<String>,
+    pub to: Option<String>,
+    /// 引起转换的回调
+    pub trigger: &'static str,
+    /// 发生时的帧号
+    pub frame: u64,
+    /// 发生时的引擎时间（秒）
+    pub time: f64,
+}
+
+impl fmt::Display for HistoryEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "[帧 {} | {:.3}s] {}: {} -> {} ({:?}, {})",
+            self.frame,
+            self.time,
+            self.region,
+            self.from.as_deref().unwrap_or("-"),
+            self.to.as_deref().unwrap_or("-"),
+            self.kind,
+            self.trigger,
+        )
+    }
+}
+
+/// 有界的转换历史（环形缓冲区），超出容量时丢弃最旧的记录
+#[derive(Debug, Clone)]
+pub struct TransitionHistory {
+    entries: VecDeque<HistoryEntry>,
+    capacity: usize,
+}
+
+impl TransitionHistory {
+    /// 创建容量为`capacity`的历史
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            entries: VecDeque::with_capacity(capacity),
+            capacity,
+        }
+    }
+
+    /// 容量
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// 修改容量，超出新容量的旧记录会被丢弃
+    pub fn set_capacity(&mut self, capacity: usize) {
+        self.capacity = capacity;
+        self.trim();
+    }
+
+    /// 记录一次状态变化
+    pub fn record(&mut self, region: &str, change: &StateChange, frame: u64, time: f64) {
+        self.entries.push_back(HistoryEntry {
+            region: region.to_string(),
+            kind: change.kind,
+            from: change.from.clone(),
+            to: change.to.clone(),
+            trigger: change.trigger,
+            frame,
+            time,
+        });
+        self.trim();
+    }
+
+    /// 所有记录，从旧到新
+    pub fn entries(&self) -> impl Iterator<Item = &HistoryEntry> {
+        self.entries.iter()
+    }
+
+    /// 最近的`count`条记录，从旧到新
+    pub fn recent(&self, count: usize) -> impl Iterator<Item = &HistoryEntry> {
+        self.entries
+            .iter()
+            .skip(self.entries.len().saturating_sub(count))
+    }
+
+    /// 记录数量
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// 是否没有记录
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// 清空记录
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    // 丢弃超出容量的旧记录
+    fn trim(&mut self) {
+        while self.entries.len() > self.capacity {
+            self.entries.pop_front();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn change(kind: ChangeKind, from: Option<&str>, to: Option<&str>) -> StateChange {
+        StateChange {
+            kind,
+            from: from.map(str::to_string),
+            to: to.map(str::to_string),
+            trigger: "process",
+        }
+    }
+
+    fn targets(history: &TransitionHistory) -> Vec<&str> {
+        history
+            .entries()
+            .map(|entry| entry.to.as_deref().unwrap_or("-"))
+            .collect()
+    }
+
+    #[test]
+    fn oldest_entries_are_evicted_in_order() {
+        let mut history = TransitionHistory::new(3);
+        for (frame, to) in ["a", "b", "c", "d", "e"].into_iter().enumerate() {
+            history.record(
+                "main",
+                &change(ChangeKind::Switch, None, Some(to)),
+                frame as u64,
+                0.0,
+            );
+        }
+        assert_eq!(history.len(), 3);
+        assert_eq!(targets(&history), ["c", "d", "e"]);
+        let frames: Vec<u64> = history.entries().map(|entry| entry.frame).collect();
+        assert_eq!(frames, [2, 3, 4]);
+
+        let recent: Vec<_> = history.recent(2).map(|entry| entry.to.as_deref()).collect();
+        assert_eq!(recent, [Some("d"), Some("e")]);
+        assert_eq!(history.recent(10).count(), 3);
+
+        history.set_capacity(1);
+        assert_eq!(targets(&history), ["e"]);
+        history.clear();
+        assert!(history.is_empty());
+    }
+
+    #[test]
+    fn zero_capacity_keeps_nothing() {
+        let mut history = TransitionHistory::new(0);
+        history.record("main", &change(ChangeKind::Switch, None, Some("a")), 0, 0.0);
+        assert!(history.is_empty());
+    }
+
+    #[test]
+    fn entries_format_every_change_kind() {
+        let mut history = TransitionHistory::new(8);
+        let changes = [
+            change(ChangeKind::Switch, None, Some("idle")),
+            change(ChangeKind::Push, Some("idle"), Some("menu")),
+            change(ChangeKind::Pop, Some("menu"), Some("idle")),
+            change(ChangeKind::Stop, Some("idle"), None),
+        ];
+        for (frame, change) in changes.iter().enumerate() {
+            history.record("locomotion", change, frame as u64 + 10, 0.25 * frame as f64);
+        }
+        let lines: Vec<String> = history.entries().map(ToString::to_string).collect();
+        assert_eq!(
+            lines,
+            [
+                "[帧 10 | 0.000s] locomotion: - -> idle (Switch, process)",
+                "[帧 11 | 0.250s] locomotion: idle -> menu (Push, process)",
+                "[帧 12 | 0.500s] locomotion: menu -> idle (Pop, process)",
+                "[帧 13 | 0.750s] locomotion: idle -> - (Stop, process)",
+            ]
+        );
+    }
+}
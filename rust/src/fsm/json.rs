@@ -0,0 +1,420 @@
+use std::fmt;
+use std::iter::Peekable;
+use std::str::Chars;
+
+/// 带行号的JSON值。定义文件用它解析，以便报告错误所在的行
+#[derive(Debug, Clone, PartialEq)]
+pub struct Json {
+    pub value: JsonValue,
+    /// 值开始的行，从1开始
+    pub line: usize,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum JsonValue {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<Json>),
+    /// 保持字段的声明顺序
+    Object(Vec<(String, Json)>),
+}
+
+impl Json {
+    /// 类型名称，用于错误信息
+    pub fn type_name(&self) -> &'static str {
+        match self.value {
+            JsonValue::Null => "null",
+            JsonValue::Bool(_) => "布尔值",
+            JsonValue::Number(_) => "数字",
+            JsonValue::String(_) => "字符串",
+            JsonValue::Array(_) => "数组",
+            JsonValue::Object(_) => "对象",
+        }
+    }
+
+    /// 对象的字段，不是对象或字段不存在时返回`None`
+    pub fn get(&self, key: &str) -> Option<&Json> {
+        match &self.value {
+            JsonValue::Object(fields) => fields
+                .iter()
+                .find(|(name, _)| name == key)
+                .map(|(_, value)| value),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match &self.value {
+            JsonValue::String(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    pub fn as_f64(&self) -> Option<f64> {
+        match self.value {
+            JsonValue::Number(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&[Json]> {
+        match &self.value {
+            JsonValue::Array(items) => Some(items),
+            _ => None,
+        }
+    }
+
+    pub fn as_object(&self) -> Option<&[(String, Json)]> {
+        match &self.value {
+            JsonValue::Object(fields) => Some(fields),
+            _ => None,
+        }
+    }
+}
+
+/// JSON语法错误
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SyntaxError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for SyntaxError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "第{}行：{}", self.line, self.message)
+    }
+}
+
+/// 解析JSON文本
+pub fn parse(source: &str) -> Result<Json, SyntaxError> {
+    let mut parser = Parser {
+        chars: source.chars().peekable(),
+        line: 1,
+    };
+    let value = parser.value()?;
+    parser.skip_whitespace();
+    match parser.chars.peek().copied() {
+        None => Ok(value),
+        Some(c) => Err(parser.error(format!("多余的字符 '{}'", c))),
+    }
+}
+
+struct Parser<'a> {
+    chars: Peekable<Chars<'a>>,
+    line: usize,
+}
+
+impl Parser<'_> {
+    fn error(&self, message: impl Into<String>) -> SyntaxError {
+        SyntaxError {
+            line: self.line,
+            message: message.into(),
+        }
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let c = self.chars.next();
+        if c == Some('\n') {
+            self.line += 1;
+        }
+        c
+    }
+
+    /// JSON只允许空格、制表符、换行和回车作为空白
+    fn skip_whitespace(&mut self) {
+        while self
+            .chars
+            .peek()
+            .is_some_and(|c| matches!(c, ' ' | '\t' | '\n' | '\r'))
+        {
+            self.next();
+        }
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), SyntaxError> {
+        self.skip_whitespace();
+        match self.next() {
+            Some(c) if c == expected => Ok(()),
+            Some(c) => Err(self.error(format!("应为 '{}'，实际为 '{}'", expected, c))),
+            None => Err(self.error(format!("应为 '{}'，但文件已结束", expected))),
+        }
+    }
+
+    fn value(&mut self) -> Result<Json, SyntaxError> {
+        self.skip_whitespace();
+        let line = self.line;
+        let value = match self.chars.peek().copied() {
+            Some('{') => self.object()?,
+            Some('[') => self.array()?,
+            Some('"') => JsonValue::String(self.string()?),
+            Some('t') => self.keyword("true", JsonValue::Bool(true))?,
+            Some('f') => self.keyword("false", JsonValue::Bool(false))?,
+            Some('n') => self.keyword("null", JsonValue::Null)?,
+            Some(c) if c == '-' || c.is_ascii_digit() => self.number()?,
+            Some(c) => return Err(self.error(format!("意外的字符 '{}'", c))),
+            None => return Err(self.error("意外的文件结尾")),
+        };
+        Ok(Json { value, line })
+    }
+
+    fn keyword(&mut self, word: &str, value: JsonValue) -> Result<JsonValue, SyntaxError> {
+        for expected in word.chars() {
+            if self.next() != Some(expected) {
+                return Err(self.error(format!("无效的字面量，应为 {}", word)));
+            }
+        }
+        Ok(value)
+    }
+
+    fn number(&mut self) -> Result<JsonValue, SyntaxError> {
+        let mut text = String::new();
+        while let Some(&c) = self.chars.peek() {
+            if !(c.is_ascii_digit() || matches!(c, '-' | '+' | '.' | 'e' | 'E')) {
+                break;
+            }
+            text.push(c);
+            self.next();
+        }
+        if !is_number(&text) {
+            return Err(self.error(format!("无效的数字 {}", text)));
+        }
+        text.parse()
+            .map(JsonValue::Number)
+            .map_err(|_| self.error(format!("无效的数字 {}", text)))
+    }
+
+    fn string(&mut self) -> Result<String, SyntaxError> {
+        self.expect('"')?;
+        let mut text = String::new();
+        loop {
+            match self.next() {
+                Some('"') => return Ok(text),
+                Some('\\') => {
+                    let escaped = match self.next() {
+                        Some('"') => '"',
+                        Some('\\') => '\\',
+                        Some('/') => '/',
+                        Some('b') => '\u{8}',
+                        Some('f') => '\u{c}',
+                        Some('n') => '\n',
+                        Some('r') => '\r',
+                        Some('t') => '\t',
+                        Some('u') => self.unicode_escape()?,
+                        _ => return Err(self.error("无效的转义字符")),
+                    };
+                    text.push(escaped);
+                }
+                Some('\n') | None => return Err(self.error("字符串没有结束")),
+                Some(c) if c < '\u{20}' => {
+                    return Err(
+                        self.error(format!("字符串中的控制字符 U+{:04X} 需要转义", c as u32))
+                    );
+                }
+                Some(c) => text.push(c),
+            }
+        }
+    }
+
+    /// `\u`之后的码元，UTF-16代理对（`\uD83D\uDE00`）合并为一个字符
+    fn unicode_escape(&mut self) -> Result<char, SyntaxError> {
+        let high = self.code_unit()?;
+        if !(0xD800..0xDC00).contains(&high) {
+            return char::from_u32(high).ok_or_else(|| self.error("孤立的低代理项\\u转义"));
+        }
+        if self.next() != Some('\\') || self.next() != Some('u') {
+            return Err(self.error("高代理项之后应为低代理项\\u转义"));
+        }
+        let low = self.code_unit()?;
+        if !(0xDC00..0xE000).contains(&low) {
+            return Err(self.error("高代理项之后应为低代理项\\u转义"));
+        }
+        char::from_u32(0x10000 + ((high - 0xD800) << 10) + (low - 0xDC00))
+            .ok_or_else(|| self.error("不支持的\\u转义"))
+    }
+
+    /// 四位十六进制数字
+    fn code_unit(&mut self) -> Result<u32, SyntaxError> {
+        let mut code = 0;
+        for _ in 0..4 {
+            let digit = self
+                .next()
+                .and_then(|c| c.to_digit(16))
+                .ok_or_else(|| self.error("无效的\\u转义"))?;
+            code = code * 16 + digit;
+        }
+        Ok(code)
+    }
+
+    fn array(&mut self) -> Result<JsonValue, SyntaxError> {
+        self.expect('[')?;
+        let mut items = Vec::new();
+        self.skip_whitespace();
+        if self.chars.peek() == Some(&']') {
+            self.next();
+            return Ok(JsonValue::Array(items));
+        }
+        loop {
+            items.push(self.value()?);
+            self.skip_whitespace();
+            match self.next() {
+                Some(',') => continue,
+                Some(']') => return Ok(JsonValue::Array(items)),
+                _ => return Err(self.error("数组元素之后应为 ',' 或 ']'")),
+            }
+        }
+    }
+
+    fn object(&mut self) -> Result<JsonValue, SyntaxError> {
+        self.expect('{')?;
+        let mut fields: Vec<(String, Json)> = Vec::new();
+        self.skip_whitespace();
+        if self.chars.peek() == Some(&'}') {
+            self.next();
+            return Ok(JsonValue::Object(fields));
+        }
+        loop {
+            self.skip_whitespace();
+            let key = self.string()?;
+            if fields.iter().any(|(name, _)| *name == key) {
+                return Err(self.error(format!("重复的字段 {}", key)));
+            }
+            self.expect(':')?;
+            let value = self.value()?;
+            fields.push((key, value));
+            self.skip_whitespace();
+            match self.next() {
+                Some(',') => continue,
+                Some('}') => return Ok(JsonValue::Object(fields)),
+                _ => return Err(self.error("字段之后应为 ',' 或 '}'")),
+            }
+        }
+    }
+}
+
+/// 是否符合JSON的数字语法：`-?(0|[1-9][0-9]*)(\.[0-9]+)?([eE][+-]?[0-9]+)?`
+fn is_number(text: &str) -> bool {
+    fn digits(text: &str) -> usize {
+        text.len() - text.trim_start_matches(|c: char| c.is_ascii_digit()).len()
+    }
+
+    let rest = text.strip_prefix('-').unwrap_or(text);
+    let integer = digits(rest);
+    if integer == 0 || (integer > 1 && rest.starts_with('0')) {
+        return false;
+    }
+    let mut rest = &rest[integer..];
+    if let Some(fraction) = rest.strip_prefix('.') {
+        let count = digits(fraction);
+        if count == 0 {
+            return false;
+        }
+        rest = &fraction[count..];
+    }
+    if let Some(exponent) = rest.strip_prefix(['e', 'E']) {
+        let exponent = exponent.strip_prefix(['+', '-']).unwrap_or(exponent);
+        let count = digits(exponent);
+        if count == 0 {
+            return false;
+        }
+        rest = &exponent[count..];
+    }
+    rest.is_empty()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn number(source: &str) -> Result<f64, SyntaxError> {
+        parse(source).map(|json| json.as_f64().expect("应为数字"))
+    }
+
+    fn string(source: &str) -> Result<String, SyntaxError> {
+        parse(source).map(|json| json.as_str().expect("应为字符串").to_string())
+    }
+
+    #[test]
+    fn accepts_json_numbers() {
+        assert_eq!(number("0"), Ok(0.0));
+        assert_eq!(number("-0"), Ok(0.0));
+        assert_eq!(number("10"), Ok(10.0));
+        assert_eq!(number("-1.5"), Ok(-1.5));
+        assert_eq!(number("0.25"), Ok(0.25));
+        assert_eq!(number("2e3"), Ok(2000.0));
+        assert_eq!(number("1.5E-2"), Ok(0.015));
+        assert_eq!(number("1e+2"), Ok(100.0));
+    }
+
+    #[test]
+    fn rejects_numbers_outside_the_grammar() {
+        for source in [
+            "01", "-01", "00", "1.", ".5", "-", "1e", "1e+", "+1", "1.2.3", "--1", "1-2",
+        ] {
+            assert!(number(source).is_err(), "{} 不应被接受", source);
+        }
+        assert_eq!(
+            number("01").unwrap_err().message,
+            "无效的数字 01".to_string()
+        );
+    }
+
+    #[test]
+    fn decodes_escapes_and_surrogate_pairs() {
+        assert_eq!(
+            string(r#""a\"b\\c\/d\n\t""#),
+            Ok("a\"b\\c/d\n\t".to_string())
+        );
+        assert_eq!(string(r#""\u00e9\u4E2D""#), Ok("é中".to_string()));
+        assert_eq!(string(r#""\uD83D\uDE00!""#), Ok("😀!".to_string()));
+        assert_eq!(string(r#""\ud83d\ude00""#), Ok("\u{1F600}".to_string()));
+    }
+
+    #[test]
+    fn rejects_unpaired_surrogates() {
+        for source in [r#""\uD83D""#, r#""\uD83Dx""#, r#""\uD83DA""#, r#""\uDE00""#] {
+            assert!(string(source).is_err(), "{} 不应被接受", source);
+        }
+        assert!(string(r#""\u12G4""#).is_err());
+    }
+
+    #[test]
+    fn values_and_errors_carry_line_numbers() {
+        let json = parse("{\n  \"a\": 1,\n  \"b\": [\n    true\n  ]\n}").unwrap();
+        assert_eq!(json.line, 1);
+        assert_eq!(json.get("a").map(|a| a.line), Some(2));
+        let b = json.get("b").unwrap();
+        assert_eq!(b.line, 3);
+        assert_eq!(b.as_array().map(|items| items[0].line), Some(4));
+
+        let error = parse("{\n  \"a\": 1\n  \"b\": 2\n}").unwrap_err();
+        assert_eq!(error.line, 3);
+        let error = parse("{\n  \"a\": 1,\n  \"a\": 2\n}").unwrap_err();
+        assert_eq!((error.line, error.message.as_str()), (3, "重复的字段 a"));
+        assert_eq!(parse("[1] x").unwrap_err().message, "多余的字符 'x'");
+    }
+
+    #[test]
+    fn only_json_whitespace_separates_tokens() {
+        assert_eq!(parse(" \t\r\n[ 1 ,\r\n 2 ]\n").map(|json| json.line), Ok(2));
+        for source in ["\u{a0}1", "[1,\u{3000}2]", "\u{b}1", "\u{feff}1", "1\u{85}"] {
+            assert!(parse(source).is_err(), "{:?} 不应被接受", source);
+        }
+    }
+
+    #[test]
+    fn rejects_control_characters_in_strings() {
+        for c in ['\u{0}', '\u{8}', '\t', '\r', '\u{1f}'] {
+            let source = format!("\"a{}b\"", c);
+            assert_eq!(
+                string(&source).unwrap_err().message,
+                format!("字符串中的控制字符 U+{:04X} 需要转义", c as u32)
+            );
+        }
+        assert!(string("\"a\nb\"").is_err());
+        // 转义后的控制字符和U+007F可以使用
+        assert_eq!(string(r#""\u0000\t""#), Ok("\u{0}\t".to_string()));
+        assert_eq!(string("\"\u{7f}\""), Ok("\u{7f}".to_string()));
+    }
+}
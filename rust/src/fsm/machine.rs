@@ -0,0 +1,908 @@
+use crate::fsm::change::{ChangeKind, StateChange};
+use crate::fsm::state::{Context, State, Transition};
+use crate::fsm::table::{Fired, TransitionTable};
+use std::any::{Any, TypeId};
+
+/// 与引擎无关的状态机：持有当前状态，并负责解析状态回调返回的转换。
+///
+/// 支持分层状态：叶子状态返回`Transition::Unhandled`的回调会沿`State::parent`逐级冒泡。
+/// 父状态切换到与当前叶子状态相同类型的状态时视为保持不变，不会重新初始化。
+///
+/// 状态机持有活动层级中每个状态的实例并以`&mut self`调用回调。
+/// 切换时保持活动的共同父状态沿用原来的实例，其余父状态由`State::parent`重新创建。
+///
+/// 状态切换时的回调顺序是固定的：
+/// 1. 旧叶子状态`exit`
+/// 2. 不再活动的旧父状态`exit`（由内到外）
+/// 3. 保持活动的共同父状态`on_transition(from, to)`（由外到内）
+/// 4. 新进入的父状态`init`（由外到内）
+/// 5. 新叶子状态`init`
+///
+/// 启动时的回调顺序：`start`先对初始状态的层级（由外到内）调用`ready`，再按上面的顺序`init`。
+/// 在`start`之前已经设置了状态时，改为调用`ready`向所有活动状态发送`ready`。
+///
+/// 状态保存在一个下推栈中，只有栈顶状态接收回调。`Push`会暂停（`pause`）当前状态并初始化新状态，
+/// `Pop`会退出栈顶状态并恢复（`resume`）下面的状态，适用于暂停、对话、过场等临时覆盖层。
+///
+/// 每次调用公开方法时，状态机会记录这次调用引起的所有状态变化，可以通过`changes`读取，
+/// 供适配层发出信号等。
+///
+/// 可以附加一张`TransitionTable`：状态回调没有切换状态时，由状态机按表中的规则切换。
+/// 通过注册ID进入的状态（`start_named`/`transition_to`/`push_named`以及转换表的切换）会记录该ID，
+/// 因此多个注册项共用同一个`State::name`时也能区分当前状态。
+/// 有状态被压栈暂停时（`depth() > 1`）不检查转换表，覆盖层只能由自己的回调切换或弹出。
+///
+/// 状态机记录每个状态的停留时间（只由`process`的`delta`累加），在回调前写入上下文。
+/// 声明了`State::timeout`的状态在停留时间到达后会收到一次`on_timeout`。
+#[derive(Debug)]
+pub struct StateMachine<C: Context> {
+    stack: Vec<Frame<C>>,
+    changes: Vec<StateChange>,
+    trigger: &'static str,
+    table: Option<TransitionTable<C>>,
+}
+
+impl<C: Context> Default for StateMachine<C> {
+    fn default() -> Self {
+        Self {
+            stack: Vec::new(),
+            changes: Vec::new(),
+            trigger: "none",
+            table: None,
+        }
+    }
+}
+
+impl<C: Context> StateMachine<C> {
+    /// 创建一台没有活动状态的状态机
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 附加转换表
+    pub fn set_table(&mut self, table: TransitionTable<C>) {
+        self.table = Some(table);
+    }
+
+    /// 附加的转换表
+    pub fn table(&self) -> Option<&TransitionTable<C>> {
+        self.table.as_ref()
+    }
+
+    /// 附加的转换表（可变）
+    pub fn table_mut(&mut self) -> Option<&mut TransitionTable<C>> {
+        self.table.as_mut()
+    }
+
+    /// 当前活动状态（栈顶状态）
+    pub fn current_state(&self) -> Option<&dyn State<C>> {
+        self.stack.last().map(|frame| frame.leaf())
+    }
+
+    /// 当前状态的停留时间（秒）。被压栈暂停期间不计时
+    pub fn time_in_state(&self) -> f64 {
+        self.stack.last().map_or(0.0, |frame| frame.elapsed)
+    }
+
+    /// 当前活动状态的名称
+    pub fn current_state_name(&self) -> Option<&str> {
+        self.current_state().map(|state| state.name())
+    }
+
+    /// 状态栈的深度
+    pub fn depth(&self) -> usize {
+        self.stack.len()
+    }
+
+    /// 当前活动状态的注册ID。
+    ///
+    /// 通过注册ID进入的状态返回记录的ID，其他状态按`State::name`在转换表的注册表中查找，找不到时返回`None`
+    pub fn current_state_id(&self) -> Option<&str> {
+        let frame = self.stack.last()?;
+        frame
+            .id
+            .as_deref()
+            .or_else(|| self.table.as_ref()?.registry().id_of(frame.leaf().name()))
+    }
+
+    /// 状态栈中所有状态的名称，从栈底到栈顶
+    pub fn stack_names(&self) -> Vec<&str> {
+        self.stack.iter().map(|frame| frame.leaf().name()).collect()
+    }
+
+    /// 当前活动状态或其任一父状态是否为`S`
+    pub fn is_in<S: State<C>>(&self) -> bool {
+        self.stack.last().is_some_and(|frame| {
+            frame
+                .states
+                .iter()
+                .any(|state| (state.as_ref() as &dyn Any).is::<S>())
+        })
+    }
+
+    /// 当前活动状态的层级路径，从最外层父状态到叶子状态
+    pub fn active_path(&self) -> Vec<&str> {
+        match self.stack.last() {
+            Some(frame) => frame.states.iter().map(|state| state.name()).collect(),
+            None => Vec::new(),
+        }
+    }
+
+    /// 当前活动状态的层级，从最外层父状态到叶子状态
+    pub fn active_states(&self) -> impl DoubleEndedIterator<Item = &dyn State<C>> + '_ {
+        self.stack
+            .last()
+            .into_iter()
+            .flat_map(|frame| frame.states.iter().map(|state| state.as_ref()))
+    }
+
+    /// 最近一次调用公开方法引起的状态变化，按发生顺序
+    pub fn changes(&self) -> &[StateChange] {
+        &self.changes
+    }
+
+    /// 是否已经有活动状态
+    pub fn has_state(&self) -> bool {
+        !self.stack.is_empty()
+    }
+
+    /// 以`state`启动状态机：先调用其层级的`ready`（由外到内），再`init`。已经有活动状态时等同于`state`
+    pub fn start(&mut self, ctx: &mut C, state: Box<dyn State<C>>) {
+        self.begin("start");
+        self.start_state(ctx, state, None);
+    }
+
+    /// 以转换表中注册的状态启动状态机，见`start`。没有转换表或ID未注册时返回`false`
+    pub fn start_named(&mut self, ctx: &mut C, id: &str) -> bool {
+        let Some(state) = self.create(id) else {
+            return false;
+        };
+        self.begin("start");
+        self.start_state(ctx, state, Some(id.to_string()));
+        true
+    }
+
+    /// 对状态栈中的所有状态（由栈底到栈顶，由外到内）调用`ready`
+    pub fn ready(&mut self, ctx: &mut C) {
+        self.begin("ready");
+        for frame in self.stack.iter_mut() {
+            ctx.set_time_in_state(frame.elapsed);
+            for state in frame.states.iter_mut() {
+                state.ready(ctx);
+            }
+        }
+    }
+
+    /// 退出栈顶状态并切换到新状态，回调顺序见类型文档
+    pub fn state(&mut self, ctx: &mut C, state: Box<dyn State<C>>) {
+        self.begin("state");
+        self.switch(ctx, state, None);
+    }
+
+    /// 切换到转换表中注册的状态，见`state`。没有转换表或ID未注册时返回`false`
+    pub fn transition_to(&mut self, ctx: &mut C, id: &str) -> bool {
+        let Some(state) = self.create(id) else {
+            return false;
+        };
+        self.begin("state");
+        self.switch(ctx, state, Some(id.to_string()));
+        true
+    }
+
+    /// 暂停当前状态，压入并初始化新状态
+    pub fn push(&mut self, ctx: &mut C, state: Box<dyn State<C>>) {
+        self.begin("push");
+        self.push_state(ctx, state, None);
+    }
+
+    /// 压入转换表中注册的状态，见`push`。没有转换表或ID未注册时返回`false`
+    pub fn push_named(&mut self, ctx: &mut C, id: &str) -> bool {
+        let Some(state) = self.create(id) else {
+            return false;
+        };
+        self.begin("push");
+        self.push_state(ctx, state, Some(id.to_string()));
+        true
+    }
+
+    /// 退出栈顶状态并恢复下面的状态。栈中只剩一个状态时不会弹出，返回`false`
+    pub fn pop(&mut self, ctx: &mut C) -> bool {
+        self.begin("pop");
+        self.pop_state(ctx)
+    }
+
+    /// 退出状态栈中的所有状态（由栈顶到栈底），状态机回到没有活动状态
+    pub fn stop(&mut self, ctx: &mut C) {
+        self.begin("stop");
+        while let Some(mut frame) = self.stack.pop() {
+            ctx.set_time_in_state(frame.elapsed);
+            frame.exit(ctx);
+            self.record(
+                ChangeKind::Stop,
+                Some(frame.leaf().name().to_string()),
+                None,
+            );
+        }
+    }
+
+    /// 分发输入事件
+    pub fn input(&mut self, ctx: &mut C, event: &C::Input) {
+        self.dispatch(ctx, Fired::Input, |state, ctx| state.input(ctx, event));
+    }
+
+    /// 分发自定义事件
+    pub fn send_event(&mut self, ctx: &mut C, event: &C::Event) {
+        self.dispatch(ctx, Fired::Event(event), |state, ctx| {
+            state.event(ctx, event)
+        });
+    }
+
+    /// 分发更新事件，并累加当前状态的停留时间
+    pub fn process(&mut self, ctx: &mut C, delta: f64) {
+        if let Some(frame) = self.stack.last_mut() {
+            frame.elapsed += delta;
+        }
+        self.dispatch(ctx, Fired::Process, |state, ctx| state.process(ctx, delta));
+    }
+
+    /// 分发物理更新事件
+    pub fn physics_process(&mut self, ctx: &mut C, delta: f64) {
+        self.dispatch(ctx, Fired::PhysicsProcess, |state, ctx| {
+            state.physics_process(ctx, delta)
+        });
+    }
+
+    /// 分发物理受力事件
+    pub fn integrate_forces(&mut self, ctx: &mut C, delta: f64) {
+        self.begin("integrate_forces");
+        self.sync_time(ctx);
+        if let Some(frame) = self.stack.last_mut() {
+            frame.leaf_mut().integrate_forces(ctx, delta);
+        }
+    }
+
+    /// 以初始状态启动，已经有活动状态时替换栈顶状态
+    fn start_state(&mut self, ctx: &mut C, state: Box<dyn State<C>>, id: Option<String>) {
+        if self.has_state() {
+            self.switch(ctx, state, id);
+            return;
+        }
+        let mut states = hierarchy(state);
+        ctx.set_time_in_state(0.0);
+        for state in states.iter_mut() {
+            state.ready(ctx);
+        }
+        let to = states.last().map(|state| state.name().to_string());
+        self.stack.push(Frame::new(states, id));
+        self.enter_top(ctx, &[]);
+        self.record(ChangeKind::Switch, None, to);
+    }
+
+    /// 从转换表的注册表创建状态
+    fn create(&self, id: &str) -> Option<Box<dyn State<C>>> {
+        self.table.as_ref()?.registry().create(id)
+    }
+
+    /// 替换栈顶状态，`id`是新状态的注册ID
+    fn switch(&mut self, ctx: &mut C, state: Box<dyn State<C>>, id: Option<String>) {
+        let mut next = hierarchy(state);
+        let next_parents: Vec<TypeId> = next[..next.len() - 1]
+            .iter()
+            .map(|state| type_id(state.as_ref()))
+            .collect();
+
+        // 退出旧状态，保留保持活动的共同父状态（由内到外）
+        self.sync_time(ctx);
+        let mut from = None;
+        let mut shared = Vec::new();
+        if let Some(mut previous) = self.stack.pop()
+            && let Some(mut leaf) = previous.states.pop()
+        {
+            leaf.exit(ctx);
+            while let Some(mut parent) = previous.states.pop() {
+                if next_parents.contains(&type_id(parent.as_ref())) {
+                    shared.push(parent);
+                } else {
+                    parent.exit(ctx);
+                }
+            }
+            if let Some(to) = next.last() {
+                for parent in shared.iter_mut().rev() {
+                    parent.on_transition(ctx, leaf.as_ref(), to.as_ref());
+                }
+            }
+            from = Some(leaf.name().to_string());
+        }
+
+        // 共同父状态沿用原来的实例
+        let mut kept = vec![false; next.len()];
+        for parent in shared {
+            if let Some(index) = next_parents
+                .iter()
+                .position(|id| *id == type_id(parent.as_ref()))
+            {
+                next[index] = parent;
+                kept[index] = true;
+            }
+        }
+
+        // 保存新状态
+        let to = next.last().map(|state| state.name().to_string());
+        self.stack.push(Frame::new(next, id));
+        self.enter_top(ctx, &kept);
+        self.record(ChangeKind::Switch, from, to);
+    }
+
+    /// 压入新状态，`id`是新状态的注册ID
+    fn push_state(&mut self, ctx: &mut C, state: Box<dyn State<C>>, id: Option<String>) {
+        let from = self.current_state_name().map(str::to_string);
+        self.sync_time(ctx);
+        if let Some(frame) = self.stack.last_mut() {
+            frame.leaf_mut().pause(ctx);
+        }
+        let to = Some(state.name().to_string());
+        self.stack.push(Frame::new(hierarchy(state), id));
+        self.enter_top(ctx, &[]);
+        self.record(ChangeKind::Push, from, to);
+    }
+
+    /// 弹出栈顶状态
+    fn pop_state(&mut self, ctx: &mut C) -> bool {
+        if self.stack.len() < 2 {
+            return false;
+        }
+        self.sync_time(ctx);
+        let Some(mut top) = self.stack.pop() else {
+            return false;
+        };
+        top.exit(ctx);
+        self.sync_time(ctx);
+        if let Some(frame) = self.stack.last_mut() {
+            frame.leaf_mut().resume(ctx);
+        }
+        let to = self.current_state_name().map(str::to_string);
+        self.record(ChangeKind::Pop, Some(top.leaf().name().to_string()), to);
+        true
+    }
+
+    /// 开始一次公开调用：清空上一次调用的状态变化
+    fn begin(&mut self, trigger: &'static str) {
+        self.changes.clear();
+        self.trigger = trigger;
+    }
+
+    /// 记录一次状态变化
+    fn record(&mut self, kind: ChangeKind, from: Option<String>, to: Option<String>) {
+        self.changes.push(StateChange {
+            kind,
+            from,
+            to,
+            trigger: self.trigger,
+        });
+    }
+
+    /// 从叶子状态开始调用回调，未处理时冒泡到父状态，然后应用得到的转换。
+    /// 所有状态都返回`Unhandled`时再检查转换表，返回`Stay`的状态阻止转换表替换自己
+    fn dispatch<F>(&mut self, ctx: &mut C, fired: Fired<'_, C>, callback: F)
+    where
+        F: Fn(&mut dyn State<C>, &mut C) -> Transition<C>,
+    {
+        self.begin(fired.name());
+        self.sync_time(ctx);
+        let Some(frame) = self.stack.last_mut() else {
+            return;
+        };
+
+        let leaf = type_id(frame.leaf());
+        let mut transition = Transition::Unhandled;
+        for (depth, state) in frame.states.iter_mut().rev().enumerate() {
+            match callback(state.as_mut(), ctx) {
+                Transition::Unhandled => {}
+                // 父状态重新选择了当前叶子状态，保持不变
+                Transition::Switch(next) if depth > 0 && type_id(next.as_ref()) == leaf => {
+                    transition = Transition::Stay;
+                    break;
+                }
+                handled => {
+                    transition = handled;
+                    break;
+                }
+            }
+        }
+
+        // on_timeout未处理时保留回调的结果，Stay仍然阻止转换表
+        let transition = match transition {
+            held @ (Transition::Unhandled | Transition::Stay)
+                if matches!(fired, Fired::Process) =>
+            {
+                match self.check_timeout(ctx) {
+                    Transition::Unhandled => held,
+                    timeout => timeout,
+                }
+            }
+            transition => transition,
+        };
+        if matches!(transition, Transition::Unhandled)
+            && let Some((id, state)) = self.evaluate_table(ctx, &fired)
+        {
+            self.switch(ctx, state, Some(id));
+            return;
+        }
+
+        self.apply(ctx, transition);
+    }
+
+    /// 停留时间到达`State::timeout`时调用一次`on_timeout`
+    fn check_timeout(&mut self, ctx: &mut C) -> Transition<C> {
+        let Some(frame) = self.stack.last_mut() else {
+            return Transition::Unhandled;
+        };
+        match frame.leaf().timeout() {
+            Some(timeout) if !frame.timed_out && frame.elapsed >= timeout => {
+                frame.timed_out = true;
+                frame.leaf_mut().on_timeout(ctx)
+            }
+            _ => Transition::Unhandled,
+        }
+    }
+
+    /// 把当前状态的停留时间写入上下文
+    fn sync_time(&self, ctx: &mut C) {
+        ctx.set_time_in_state(self.time_in_state());
+    }
+
+    /// 检查转换表，返回第一条匹配规则的目标ID和新创建的目标状态。覆盖层在栈顶时不检查，以免规则替换覆盖层
+    fn evaluate_table(&self, ctx: &C, fired: &Fired<'_, C>) -> Option<(String, Box<dyn State<C>>)> {
+        let table = self.table.as_ref()?;
+        if self.stack.len() > 1 {
+            return None;
+        }
+        let to = table.evaluate(ctx, self.current_state_id(), fired)?;
+        let state = table.registry().create(to)?;
+        Some((to.to_string(), state))
+    }
+
+    /// 应用状态回调返回的转换
+    fn apply(&mut self, ctx: &mut C, transition: Transition<C>) {
+        match transition {
+            Transition::Unhandled | Transition::Stay => {}
+            Transition::Switch(next) => self.switch(ctx, next, None),
+            Transition::Push(next) => self.push_state(ctx, next, None),
+            Transition::Pop => {
+                self.pop_state(ctx);
+            }
+        }
+    }
+
+    /// 初始化栈顶状态：由外到内初始化父状态和叶子状态，跳过沿用原实例的共同父状态（`kept`）
+    fn enter_top(&mut self, ctx: &mut C, kept: &[bool]) {
+        self.sync_time(ctx);
+        let Some(frame) = self.stack.last_mut() else {
+            return;
+        };
+        for (index, state) in frame.states.iter_mut().enumerate() {
+            if !kept.get(index).copied().unwrap_or(false) {
+                state.init(ctx);
+            }
+        }
+    }
+}
+
+/// 状态栈中的一帧：活动层级中的状态实例（从最外层父状态到叶子状态）、叶子状态的注册ID及停留时间
+#[derive(Debug)]
+struct Frame<C: Context> {
+    states: Vec<Box<dyn State<C>>>,
+    /// 通过注册ID进入时记录的ID
+    id: Option<String>,
+    elapsed: f64,
+    timed_out: bool,
+}
+
+impl<C: Context> Frame<C> {
+    fn new(states: Vec<Box<dyn State<C>>>, id: Option<String>) -> Self {
+        Self {
+            states,
+            id,
+            elapsed: 0.0,
+            timed_out: false,
+        }
+    }
+
+    /// 叶子状态
+    fn leaf(&self) -> &dyn State<C> {
+        self.states.last().expect("状态帧至少包含叶子状态").as_ref()
+    }
+
+    /// 叶子状态（可变）
+    fn leaf_mut(&mut self) -> &mut dyn State<C> {
+        self.states
+            .last_mut()
+            .expect("状态帧至少包含叶子状态")
+            .as_mut()
+    }
+
+    /// 退出叶子状态及其所有父状态（由内到外）
+    fn exit(&mut self, ctx: &mut C) {
+        for state in self.states.iter_mut().rev() {
+            state.exit(ctx);
+        }
+    }
+}
+
+/// 创建状态的所有父状态实例，返回从最外层父状态到叶子状态的层级
+fn hierarchy<C: Context>(state: Box<dyn State<C>>) -> Vec<Box<dyn State<C>>> {
+    let mut states = vec![state];
+    while let Some(parent) = states.last().and_then(|state| state.parent()) {
+        states.push(parent);
+    }
+    states.reverse();
+    states
+}
+
+/// 状态的具体类型
+fn type_id<C: Context>(state: &dyn State<C>) -> TypeId {
+    (state as &dyn Any).type_id()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fsm::registry::StateRegistry;
+    use crate::fsm::table::{Rule, TableError, Trigger};
+
+    /// 记录回调顺序的上下文
+    #[derive(Debug, Default)]
+    struct Log {
+        calls: Vec<String>,
+        time_in_state: f64,
+    }
+
+    impl Log {
+        fn take(&mut self) -> Vec<String> {
+            std::mem::take(&mut self.calls)
+        }
+    }
+
+    impl Context for Log {
+        type Input = ();
+        type Event = &'static str;
+
+        fn time_in_state(&self) -> f64 {
+            self.time_in_state
+        }
+
+        fn set_time_in_state(&mut self, seconds: f64) {
+            self.time_in_state = seconds;
+        }
+    }
+
+    /// 声明记录生命周期回调的状态，`event`把事件名映射为转换
+    macro_rules! logged_state {
+        ($name:ident $(: $parent:ident)?, |$event:ident| $transition:expr) => {
+            #[derive(Debug, Default)]
+            struct $name;
+
+            impl State<Log> for $name {
+                $(
+                    fn parent(&self) -> Option<Box<dyn State<Log>>> {
+                        Some(Box::new($parent))
+                    }
+                )?
+
+                fn ready(&mut self, ctx: &mut Log) {
+                    ctx.calls.push(format!("{}.ready", self.name()));
+                }
+
+                fn init(&mut self, ctx: &mut Log) {
+                    ctx.calls.push(format!("{}.init", self.name()));
+                }
+
+                fn exit(&mut self, ctx: &mut Log) {
+                    ctx.calls.push(format!("{}.exit", self.name()));
+                }
+
+                fn pause(&mut self, ctx: &mut Log) {
+                    ctx.calls.push(format!("{}.pause", self.name()));
+                }
+
+                fn resume(&mut self, ctx: &mut Log) {
+                    ctx.calls.push(format!("{}.resume", self.name()));
+                }
+
+                fn on_transition(&mut self, ctx: &mut Log, from: &dyn State<Log>, to: &dyn State<Log>) {
+                    ctx.calls.push(format!(
+                        "{}.on_transition({}->{})",
+                        self.name(),
+                        from.name(),
+                        to.name()
+                    ));
+                }
+
+                fn event(&mut self, _ctx: &mut Log, $event: &&'static str) -> Transition<Log> {
+                    $transition
+                }
+            }
+        };
+    }
+
+    logged_state!(Ground, |event| match *event {
+        "menu" => Transition::push(Menu),
+        "swim" => Transition::switch(Swim),
+        _ => Transition::Unhandled,
+    });
+    logged_state!(Walk: Ground, |event| match *event {
+        "jump" => Transition::switch(Jump),
+        _ => Transition::Unhandled,
+    });
+    logged_state!(Jump: Ground, |_event| Transition::Unhandled);
+    logged_state!(Swim, |_event| Transition::Unhandled);
+    logged_state!(Menu, |event| match *event {
+        "close" => Transition::Pop,
+        _ => Transition::Stay,
+    });
+
+    fn started() -> (StateMachine<Log>, Log) {
+        let mut machine = StateMachine::new();
+        let mut log = Log::default();
+        machine.start(&mut log, Box::new(Walk));
+        (machine, log)
+    }
+
+    #[test]
+    fn start_calls_ready_then_init_from_outside_in() {
+        let (machine, mut log) = started();
+        assert_eq!(
+            log.take(),
+            ["Ground.ready", "Walk.ready", "Ground.init", "Walk.init"]
+        );
+        assert_eq!(machine.active_path(), ["Ground", "Walk"]);
+    }
+
+    #[test]
+    fn switch_between_siblings_keeps_shared_parent() {
+        let (mut machine, mut log) = started();
+        log.take();
+        machine.send_event(&mut log, &"jump");
+        assert_eq!(
+            log.take(),
+            ["Walk.exit", "Ground.on_transition(Walk->Jump)", "Jump.init"]
+        );
+        assert_eq!(machine.current_state_name(), Some("Jump"));
+    }
+
+    #[test]
+    fn switch_out_of_parent_exits_it_after_leaf() {
+        let (mut machine, mut log) = started();
+        log.take();
+        // 叶子状态未处理，冒泡到父状态
+        machine.send_event(&mut log, &"swim");
+        assert_eq!(log.take(), ["Walk.exit", "Ground.exit", "Swim.init"]);
+        assert_eq!(machine.active_path(), ["Swim"]);
+    }
+
+    #[test]
+    fn push_pauses_and_pop_resumes() {
+        let (mut machine, mut log) = started();
+        log.take();
+
+        machine.send_event(&mut log, &"menu");
+        assert_eq!(log.take(), ["Walk.pause", "Menu.init"]);
+        assert_eq!(machine.stack_names(), ["Walk", "Menu"]);
+        assert_eq!(machine.changes()[0].kind, ChangeKind::Push);
+
+        // 暂停的状态收不到事件
+        machine.send_event(&mut log, &"jump");
+        assert!(log.take().is_empty());
+
+        machine.send_event(&mut log, &"close");
+        assert_eq!(log.take(), ["Menu.exit", "Walk.resume"]);
+        assert_eq!(machine.stack_names(), ["Walk"]);
+        assert_eq!(machine.changes()[0].kind, ChangeKind::Pop);
+    }
+
+    #[test]
+    fn pop_keeps_the_last_state() {
+        let (mut machine, mut log) = started();
+        log.take();
+        assert!(!machine.pop(&mut log));
+        assert!(log.take().is_empty());
+        assert_eq!(machine.current_state_name(), Some("Walk"));
+    }
+
+    #[test]
+    fn time_in_state_is_the_sum_of_process_deltas() {
+        let (mut machine, mut log) = started();
+        let deltas = [0.016, 0.017, 0.033, 0.25];
+        for delta in deltas {
+            machine.process(&mut log, delta);
+            machine.physics_process(&mut log, 1.0);
+        }
+        let total: f64 = deltas.iter().sum();
+        assert!((machine.time_in_state() - total).abs() < 1e-9);
+        assert!((log.time_in_state - total).abs() < 1e-9);
+
+        // 切换后重新计时
+        machine.send_event(&mut log, &"jump");
+        machine.process(&mut log, 0.1);
+        assert!((machine.time_in_state() - 0.1).abs() < 1e-9);
+    }
+
+    #[test]
+    fn stop_exits_from_inside_out() {
+        let (mut machine, mut log) = started();
+        machine.push(&mut log, Box::new(Menu));
+        log.take();
+        machine.stop(&mut log);
+        assert_eq!(log.take(), ["Menu.exit", "Walk.exit", "Ground.exit"]);
+        assert!(!machine.has_state());
+    }
+
+    #[test]
+    fn wildcard_rules_leave_overlays_alone() {
+        let mut registry = StateRegistry::new();
+        registry.register_default::<Walk>("walk");
+        registry.register_default::<Swim>("swim");
+        registry.register_default::<Menu>("menu");
+        let mut table = TransitionTable::new(registry);
+        table.add(Rule::new(None, Trigger::Process, "swim"));
+        assert_eq!(
+            table.validate("walk"),
+            Err(vec![TableError::Unreachable("menu".to_string())])
+        );
+        table.add_entry("menu");
+        assert_eq!(table.validate("walk"), Ok(()));
+
+        let (mut machine, mut log) = started();
+        machine.set_table(table);
+        machine.push(&mut log, Box::new(Menu));
+        machine.process(&mut log, 0.1);
+        assert_eq!(machine.stack_names(), ["Walk", "Menu"]);
+
+        machine.pop(&mut log);
+        machine.process(&mut log, 0.1);
+        assert_eq!(machine.stack_names(), ["Swim"]);
+    }
+
+    #[test]
+    fn named_states_keep_their_registry_id() {
+        // 两个注册项创建同名的状态，按名称反查只能找到第一个
+        let mut registry = StateRegistry::new();
+        registry.register_default::<Walk>("walk");
+        registry.register_default::<Walk>("stroll");
+        registry.register_default::<Swim>("swim");
+        let mut table = TransitionTable::new(registry);
+        table.add(Rule::new(Some("stroll"), Trigger::After(0.5), "swim"));
+
+        let mut machine = StateMachine::new();
+        let mut log = Log::default();
+        machine.set_table(table);
+        assert!(machine.start_named(&mut log, "stroll"));
+        assert_eq!(machine.current_state_id(), Some("stroll"));
+
+        machine.process(&mut log, 0.6);
+        assert_eq!(machine.current_state_id(), Some("swim"));
+        assert!(machine.transition_to(&mut log, "stroll"));
+        assert_eq!(machine.current_state_id(), Some("stroll"));
+        assert!(!machine.transition_to(&mut log, "fly"));
+
+        // 工厂失败时保持当前状态
+        if let Some(table) = machine.table_mut() {
+            table.registry_mut().register_fallible("gone", || None);
+        }
+        log.take();
+        assert!(!machine.transition_to(&mut log, "gone"));
+        assert!(log.take().is_empty());
+        assert_eq!(machine.current_state_id(), Some("stroll"));
+
+        // 状态回调直接创建的状态按名称反查
+        machine.state(&mut log, Box::new(Walk));
+        assert_eq!(machine.current_state_id(), Some("walk"));
+    }
+
+    #[test]
+    fn stay_blocks_the_table_and_unhandled_falls_through() {
+        let mut registry = StateRegistry::new();
+        registry.register_default::<Menu>("menu");
+        registry.register_default::<Swim>("swim");
+        registry.register_default::<Walk>("walk");
+        let mut table = TransitionTable::new(registry);
+        table.add(Rule::new(
+            None,
+            Trigger::Event(Box::new(|event| *event == "dive")),
+            "walk",
+        ));
+
+        let mut machine = StateMachine::new();
+        let mut log = Log::default();
+        machine.set_table(table);
+
+        // Menu对未知事件返回Stay，规则不替换它
+        assert!(machine.start_named(&mut log, "menu"));
+        machine.send_event(&mut log, &"dive");
+        assert_eq!(machine.current_state_id(), Some("menu"));
+
+        // Swim返回Unhandled，由规则切换
+        assert!(machine.transition_to(&mut log, "swim"));
+        machine.send_event(&mut log, &"dive");
+        assert_eq!(machine.current_state_id(), Some("walk"));
+    }
+
+    /// 停留`timeout`秒后记录一次`on_timeout`的状态
+    macro_rules! timed_state {
+        ($name:ident, $timeout:expr) => {
+            #[derive(Debug, Default)]
+            struct $name;
+
+            impl State<Log> for $name {
+                fn timeout(&self) -> Option<f64> {
+                    Some($timeout)
+                }
+
+                fn on_timeout(&mut self, ctx: &mut Log) -> Transition<Log> {
+                    ctx.calls.push(format!("{}.timeout", self.name()));
+                    Transition::Unhandled
+                }
+            }
+        };
+    }
+
+    timed_state!(Dash, 0.5);
+    timed_state!(Guard, 1.0);
+
+    fn timed_machine() -> (StateMachine<Log>, Log) {
+        let mut registry = StateRegistry::new();
+        registry.register_default::<Dash>("dash");
+        registry.register_default::<Guard>("guard");
+        let mut machine = StateMachine::new();
+        machine.set_table(TransitionTable::new(registry));
+        (machine, Log::default())
+    }
+
+    #[test]
+    fn on_timeout_fires_once_per_entry() {
+        let (mut machine, mut log) = timed_machine();
+        assert!(machine.start_named(&mut log, "dash"));
+        machine.process(&mut log, 0.4);
+        assert!(log.take().is_empty());
+        for _ in 0..5 {
+            machine.process(&mut log, 0.2);
+        }
+        assert_eq!(log.take(), ["Dash.timeout"]);
+
+        // 重新进入（包括切换到自身）时重新计时
+        assert!(machine.transition_to(&mut log, "dash"));
+        machine.process(&mut log, 0.3);
+        assert!(log.take().is_empty());
+        machine.process(&mut log, 0.3);
+        machine.process(&mut log, 0.3);
+        assert_eq!(log.take(), ["Dash.timeout"]);
+    }
+
+    #[test]
+    fn pushed_states_time_separately_and_paused_states_resume_their_timer() {
+        let (mut machine, mut log) = timed_machine();
+        assert!(machine.start_named(&mut log, "guard"));
+        machine.process(&mut log, 0.6);
+
+        // 压入的状态有自己的计时，被暂停的状态不计时
+        assert!(machine.push_named(&mut log, "dash"));
+        machine.process(&mut log, 0.5);
+        machine.process(&mut log, 0.5);
+        assert_eq!(log.take(), ["Dash.timeout"]);
+        assert!(machine.pop(&mut log));
+        assert_eq!(machine.time_in_state(), 0.6);
+
+        // 每次压入都是新的进入
+        assert!(machine.push_named(&mut log, "dash"));
+        machine.process(&mut log, 0.5);
+        assert_eq!(log.take(), ["Dash.timeout"]);
+        assert!(machine.pop(&mut log));
+
+        // 恢复后从暂停时的停留时间继续，到达后只触发一次
+        machine.process(&mut log, 0.3);
+        assert!(log.take().is_empty());
+        machine.process(&mut log, 0.2);
+        machine.process(&mut log, 0.2);
+        assert_eq!(log.take(), ["Guard.timeout"]);
+    }
+}
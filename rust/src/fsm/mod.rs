@@ -0,0 +1,12 @@
+pub mod change;
+pub mod decision;
+pub mod definition;
+pub mod enum_machine;
+pub mod export;
+pub mod history;
+pub mod json;
+pub mod machine;
+pub mod region;
+pub mod registry;
+pub mod state;
+pub mod table;
@@ -0,0 +1,377 @@
+use crate::fsm::change::StateChange;
+use crate::fsm::machine::StateMachine;
+use crate::fsm::state::{Context, State};
+
+/// 各并行区域活动状态的快照，供状态在守卫条件中观察其他区域
+#[derive(Debug, Clone, Default)]
+pub struct RegionSnapshot {
+    regions: Vec<(String, Vec<String>)>,
+}
+
+impl RegionSnapshot {
+    /// 区域的活动状态路径，从最外层父状态到叶子状态
+    pub fn active_path(&self, region: &str) -> Option<&[String]> {
+        self.regions
+            .iter()
+            .find(|(name, _)| name == region)
+            .map(|(_, path)| path.as_slice())
+    }
+
+    /// 区域当前的叶子状态名称
+    pub fn current_state(&self, region: &str) -> Option<&str> {
+        self.active_path(region)
+            .and_then(|path| path.last())
+            .map(String::as_str)
+    }
+
+    /// 区域中名为`state`的状态（叶子状态或其父状态）是否处于活动状态
+    pub fn is_active(&self, region: &str, state: &str) -> bool {
+        self.active_path(region)
+            .is_some_and(|path| path.iter().any(|name| name == state))
+    }
+
+    /// 替换区域的活动状态路径
+    fn update(&mut self, region: &str, path: Vec<&str>) {
+        let path = path.into_iter().map(str::to_string).collect();
+        match self.regions.iter_mut().find(|(name, _)| name == region) {
+            Some((_, existing)) => *existing = path,
+            None => self.regions.push((region.to_string(), path)),
+        }
+    }
+}
+
+/// 并行区域：拥有自己当前状态的一台状态机
+#[derive(Debug)]
+struct Region<C: Context> {
+    name: String,
+    machine: StateMachine<C>,
+}
+
+/// 正交（并行）状态机：多个区域同时运行，接收相同的回调。
+///
+/// 回调按区域添加的顺序分发。区域的状态发生变化后，上下文中`RegionSnapshot`里该区域的路径会立即更新，
+/// 因此状态总是能看到排在前面的区域在本帧中切换后的状态。
+///
+/// 传入状态实例的方法（`state`、`start`、`push`）在区域不存在时先创建它；按注册ID操作的方法
+/// （`start_named`、`transition_to`、`push_named`）依赖区域的转换表，区域不存在时与ID未注册一样返回`false`，
+/// 不会创建区域。
+#[derive(Debug)]
+pub struct ParallelMachine<C: Context> {
+    regions: Vec<Region<C>>,
+    changes: Vec<(String, StateChange)>,
+}
+
+impl<C: Context> Default for ParallelMachine<C> {
+    fn default() -> Self {
+        Self {
+            regions: Vec::new(),
+            changes: Vec::new(),
+        }
+    }
+}
+
+impl<C: Context> ParallelMachine<C> {
+    /// 创建一台没有区域的并行状态机
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 添加一个空区域。区域已存在时不做任何事
+    pub fn add_region(&mut self, name: &str) {
+        if self.region(name).is_none() {
+            self.regions.push(Region {
+                name: name.to_string(),
+                machine: StateMachine::new(),
+            });
+        }
+    }
+
+    /// 所有区域的名称，按分发顺序
+    pub fn region_names(&self) -> Vec<&str> {
+        self.regions
+            .iter()
+            .map(|region| region.name.as_str())
+            .collect()
+    }
+
+    /// 获取区域的状态机
+    pub fn region(&self, name: &str) -> Option<&StateMachine<C>> {
+        self.regions
+            .iter()
+            .find(|region| region.name == name)
+            .map(|region| &region.machine)
+    }
+
+    /// 获取区域的状态机（可变）
+    pub fn region_mut(&mut self, name: &str) -> Option<&mut StateMachine<C>> {
+        self.regions
+            .iter_mut()
+            .find(|region| region.name == name)
+            .map(|region| &mut region.machine)
+    }
+
+    /// 主区域（第一个添加的区域）
+    pub fn primary(&self) -> Option<&StateMachine<C>> {
+        self.regions.first().map(|region| &region.machine)
+    }
+
+    /// 主区域（可变）
+    pub fn primary_mut(&mut self) -> Option<&mut StateMachine<C>> {
+        self.regions.first_mut().map(|region| &mut region.machine)
+    }
+
+    /// 最近一次调用公开方法引起的状态变化，附带所在区域的名称
+    pub fn changes(&self) -> &[(String, StateChange)] {
+        &self.changes
+    }
+
+    /// 切换区域的状态，区域不存在时先创建它
+    pub fn state(&mut self, ctx: &mut C, region: &str, state: Box<dyn State<C>>) {
+        self.add_region(region);
+        self.call_region(ctx, region, |machine, ctx| machine.state(ctx, state));
+    }
+
+    /// 以`state`启动区域（先`ready`再`init`），区域不存在时先创建它
+    pub fn start(&mut self, ctx: &mut C, region: &str, state: Box<dyn State<C>>) {
+        self.add_region(region);
+        self.call_region(ctx, region, |machine, ctx| machine.start(ctx, state));
+    }
+
+    /// 对所有区域的活动状态调用`ready`
+    pub fn ready(&mut self, ctx: &mut C) {
+        self.dispatch(ctx, |machine, ctx| machine.ready(ctx));
+    }
+
+    /// 以转换表中注册的状态启动区域，见`StateMachine::start_named`。区域不存在时返回`false`
+    pub fn start_named(&mut self, ctx: &mut C, region: &str, id: &str) -> bool {
+        self.call_region(ctx, region, |machine, ctx| machine.start_named(ctx, id))
+            .unwrap_or(false)
+    }
+
+    /// 按注册ID切换区域的状态，见`StateMachine::transition_to`。区域不存在时返回`false`
+    pub fn transition_to(&mut self, ctx: &mut C, region: &str, id: &str) -> bool {
+        self.call_region(ctx, region, |machine, ctx| machine.transition_to(ctx, id))
+            .unwrap_or(false)
+    }
+
+    /// 按注册ID压入区域的状态，见`StateMachine::push_named`。区域不存在时返回`false`
+    pub fn push_named(&mut self, ctx: &mut C, region: &str, id: &str) -> bool {
+        self.call_region(ctx, region, |machine, ctx| machine.push_named(ctx, id))
+            .unwrap_or(false)
+    }
+
+    /// 暂停区域的当前状态并压入新状态，区域不存在时先创建它
+    pub fn push(&mut self, ctx: &mut C, region: &str, state: Box<dyn State<C>>) {
+        self.add_region(region);
+        self.call_region(ctx, region, |machine, ctx| machine.push(ctx, state));
+    }
+
+    /// 弹出区域的栈顶状态，返回是否弹出。区域不存在时返回`false`
+    pub fn pop(&mut self, ctx: &mut C, region: &str) -> bool {
+        self.call_region(ctx, region, |machine, ctx| machine.pop(ctx))
+            .unwrap_or(false)
+    }
+
+    /// 当前所有区域活动状态的快照
+    pub fn snapshot(&self) -> RegionSnapshot {
+        RegionSnapshot {
+            regions: self
+                .regions
+                .iter()
+                .map(|region| {
+                    let path = region.machine.active_path();
+                    (
+                        region.name.clone(),
+                        path.into_iter().map(str::to_string).collect(),
+                    )
+                })
+                .collect(),
+        }
+    }
+
+    /// 分发输入事件
+    pub fn input(&mut self, ctx: &mut C, event: &C::Input) {
+        self.dispatch(ctx, |machine, ctx| machine.input(ctx, event));
+    }
+
+    /// 向所有区域分发自定义事件
+    pub fn send_event(&mut self, ctx: &mut C, event: &C::Event) {
+        self.dispatch(ctx, |machine, ctx| machine.send_event(ctx, event));
+    }
+
+    /// 分发更新事件
+    pub fn process(&mut self, ctx: &mut C, delta: f64) {
+        self.dispatch(ctx, |machine, ctx| machine.process(ctx, delta));
+    }
+
+    /// 分发物理更新事件
+    pub fn physics_process(&mut self, ctx: &mut C, delta: f64) {
+        self.dispatch(ctx, |machine, ctx| machine.physics_process(ctx, delta));
+    }
+
+    /// 分发物理受力事件
+    pub fn integrate_forces(&mut self, ctx: &mut C, delta: f64) {
+        self.dispatch(ctx, |machine, ctx| machine.integrate_forces(ctx, delta));
+    }
+
+    /// 退出所有区域的状态（按添加顺序的逆序）
+    pub fn stop(&mut self, ctx: &mut C) {
+        self.changes.clear();
+        for index in (0..self.regions.len()).rev() {
+            self.regions[index].machine.stop(ctx);
+            self.collect(index);
+            self.refresh(ctx, index);
+        }
+    }
+
+    /// 在单个区域上调用方法，区域不存在时返回`None`
+    fn call_region<T, F>(&mut self, ctx: &mut C, region: &str, callback: F) -> Option<T>
+    where
+        F: FnOnce(&mut StateMachine<C>, &mut C) -> T,
+    {
+        self.changes.clear();
+        let index = self.regions.iter().position(|r| r.name == region)?;
+        let result = callback(&mut self.regions[index].machine, ctx);
+        self.collect(index);
+        self.refresh(ctx, index);
+        Some(result)
+    }
+
+    /// 依次在每个区域上调用回调
+    fn dispatch<F>(&mut self, ctx: &mut C, callback: F)
+    where
+        F: Fn(&mut StateMachine<C>, &mut C),
+    {
+        self.changes.clear();
+        for index in 0..self.regions.len() {
+            callback(&mut self.regions[index].machine, ctx);
+            self.collect(index);
+            self.refresh(ctx, index);
+        }
+    }
+
+    /// 收集区域在最近一次调用中的状态变化
+    fn collect(&mut self, index: usize) {
+        let region = &self.regions[index];
+        for change in region.machine.changes() {
+            self.changes.push((region.name.clone(), change.clone()));
+        }
+    }
+
+    /// 区域在最近一次调用中切换了状态时，更新上下文中该区域的路径（上下文不支持时跳过）
+    fn refresh(&self, ctx: &mut C, index: usize) {
+        let region = &self.regions[index];
+        if region.machine.changes().is_empty() {
+            return;
+        }
+        if let Some(snapshot) = ctx.regions_mut() {
+            snapshot.update(&region.name, region.machine.active_path());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fsm::registry::StateRegistry;
+    use crate::fsm::state::Transition;
+    use crate::fsm::table::TransitionTable;
+
+    /// 支持区域快照的上下文，`seen`记录`watcher`区域观察到的`mover`区域状态
+    #[derive(Debug, Default)]
+    struct World {
+        regions: RegionSnapshot,
+        seen: Vec<Option<String>>,
+    }
+
+    impl Context for World {
+        type Input = ();
+        type Event = ();
+
+        fn regions(&self) -> Option<&RegionSnapshot> {
+            Some(&self.regions)
+        }
+
+        fn regions_mut(&mut self) -> Option<&mut RegionSnapshot> {
+            Some(&mut self.regions)
+        }
+    }
+
+    #[derive(Debug)]
+    struct Standing;
+
+    #[derive(Debug)]
+    struct Walking;
+
+    #[derive(Debug)]
+    struct Watcher;
+
+    impl State<World> for Standing {
+        fn process(&mut self, _ctx: &mut World, _delta: f64) -> Transition<World> {
+            Transition::switch(Walking)
+        }
+    }
+
+    impl State<World> for Walking {}
+
+    impl State<World> for Watcher {
+        fn process(&mut self, ctx: &mut World, _delta: f64) -> Transition<World> {
+            let state = ctx.regions.current_state("mover").map(str::to_string);
+            ctx.seen.push(state);
+            Transition::Stay
+        }
+    }
+
+    #[test]
+    fn later_regions_see_switches_from_the_same_frame() {
+        let mut machine = ParallelMachine::new();
+        let mut world = World::default();
+        machine.start(&mut world, "mover", Box::new(Standing));
+        machine.start(&mut world, "watcher", Box::new(Watcher));
+        assert_eq!(world.regions.current_state("mover"), Some("Standing"));
+
+        machine.process(&mut world, 0.016);
+        machine.process(&mut world, 0.016);
+        assert_eq!(
+            world.seen,
+            [Some("Walking".to_string()), Some("Walking".to_string())]
+        );
+        assert_eq!(
+            world.regions.active_path("watcher"),
+            Some(&["Watcher".to_string()][..])
+        );
+
+        machine.stop(&mut world);
+        assert_eq!(world.regions.current_state("mover"), None);
+    }
+
+    #[test]
+    fn state_instances_create_regions_and_ids_require_them() {
+        let mut machine = ParallelMachine::new();
+        let mut world = World::default();
+
+        // 按ID操作不创建区域
+        assert!(!machine.start_named(&mut world, "mover", "walking"));
+        assert!(!machine.transition_to(&mut world, "mover", "walking"));
+        assert!(!machine.push_named(&mut world, "mover", "walking"));
+        assert!(!machine.pop(&mut world, "mover"));
+        assert!(machine.region_names().is_empty());
+
+        // 传入状态实例时创建区域
+        machine.push(&mut world, "overlay", Box::new(Walking));
+        machine.state(&mut world, "mover", Box::new(Standing));
+        assert_eq!(machine.region_names(), ["overlay", "mover"]);
+        assert_eq!(world.regions.current_state("overlay"), Some("Walking"));
+        assert_eq!(world.regions.current_state("mover"), Some("Standing"));
+
+        let mut registry = StateRegistry::new();
+        registry.register("walking", || Box::new(Walking));
+        if let Some(mover) = machine.region_mut("mover") {
+            mover.set_table(TransitionTable::new(registry));
+        }
+        assert!(machine.push_named(&mut world, "mover", "walking"));
+        assert_eq!(world.regions.current_state("mover"), Some("Walking"));
+        assert!(machine.pop(&mut world, "mover"));
+        assert!(!machine.pop(&mut world, "mover"));
+    }
+}
@@ -0,0 +1,102 @@
+use crate::fsm::state::{Context, State};
+use std::fmt;
+
+/// 构造状态实例的工厂
+pub type StateFactory<C> = Box<dyn Fn() -> Box<dyn State<C>> + Send + Sync>;
+
+/// 可能失败的工厂，依赖的外部对象（例如状态节点）不存在时返回`None`
+type FallibleFactory<C> = Box<dyn Fn() -> Option<Box<dyn State<C>>> + Send + Sync>;
+
+/// 已注册的状态
+struct Entry<C: Context> {
+    id: String,
+    state_name: String,
+    factory: FallibleFactory<C>,
+}
+
+/// 状态注册表：把稳定的字符串ID映射到状态工厂，用于按名称切换状态
+pub struct StateRegistry<C: Context> {
+    entries: Vec<Entry<C>>,
+}
+
+impl<C: Context> Default for StateRegistry<C> {
+    fn default() -> Self {
+        Self {
+            entries: Vec::new(),
+        }
+    }
+}
+
+impl<C: Context> fmt::Debug for StateRegistry<C> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list()
+            .entries(self.entries.iter().map(|entry| &entry.id))
+            .finish()
+    }
+}
+
+impl<C: Context> StateRegistry<C> {
+    /// 创建一个空的注册表
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 注册状态工厂。ID已存在时替换原来的工厂
+    pub fn register<F>(&mut self, id: &str, factory: F)
+    where
+        F: Fn() -> Box<dyn State<C>> + Send + Sync + 'static,
+    {
+        self.register_fallible(id, move || Some(factory()));
+    }
+
+    /// 注册可能失败的状态工厂，工厂返回`None`时`create`也返回`None`，状态机保持当前状态。
+    /// ID已存在时替换原来的工厂
+    pub fn register_fallible<F>(&mut self, id: &str, factory: F)
+    where
+        F: Fn() -> Option<Box<dyn State<C>>> + Send + Sync + 'static,
+    {
+        let state_name = factory()
+            .map(|state| state.name().to_string())
+            .unwrap_or_default();
+        let entry = Entry {
+            id: id.to_string(),
+            state_name,
+            factory: Box::new(factory),
+        };
+        match self.entries.iter_mut().find(|entry| entry.id == id) {
+            Some(existing) => *existing = entry,
+            None => self.entries.push(entry),
+        }
+    }
+
+    /// 注册可以用`Default`构造的状态
+    pub fn register_default<S: State<C> + Default>(&mut self, id: &str) {
+        self.register(id, || Box::new(S::default()));
+    }
+
+    /// 按ID构造一个新的状态实例，ID未注册或工厂失败时返回`None`
+    pub fn create(&self, id: &str) -> Option<Box<dyn State<C>>> {
+        self.entries
+            .iter()
+            .find(|entry| entry.id == id)
+            .and_then(|entry| (entry.factory)())
+    }
+
+    /// 是否注册了该ID
+    pub fn contains(&self, id: &str) -> bool {
+        self.entries.iter().any(|entry| entry.id == id)
+    }
+
+    /// 所有已注册的ID，按注册顺序
+    pub fn ids(&self) -> Vec<&str> {
+        self.entries.iter().map(|entry| entry.id.as_str()).collect()
+    }
+
+    /// 根据状态名称（`State::name`）查找注册时使用的ID
+    pub fn id_of(&self, state_name: &str) -> Option<&str> {
+        self.entries
+            .iter()
+            .find(|entry| entry.state_name == state_name)
+            .map(|entry| entry.id.as_str())
+    }
+}
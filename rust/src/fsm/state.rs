@@ -0,0 +1,148 @@
+use crate::fsm::decision::Snapshot;
+use crate::fsm::region::RegionSnapshot;
+use std::any::Any;
+use std::fmt::Debug;
+
+/// 上下文是状态回调时可访问的外部环境（所有者、共享资源等）。
+///
+/// 核心状态机只依赖这个特性，因此可以在没有Godot引擎的情况下运行和测试。
+pub trait Context: 'static {
+    /// 输入事件类型，对应`_input()`回调的参数
+    type Input;
+
+    /// 自定义游戏事件类型（受到伤害、拾取物品、对话结束等），由`send_event`分发
+    type Event;
+
+    /// 并行区域的活动状态快照，不支持并行区域的上下文返回`None`
+    fn regions(&self) -> Option<&RegionSnapshot> {
+        None
+    }
+
+    /// 并行区域的活动状态快照（可变），由`ParallelMachine`刷新
+    fn regions_mut(&mut self) -> Option<&mut RegionSnapshot> {
+        None
+    }
+
+    /// 当前状态的停留时间（秒），不记录时间的上下文返回`0.0`
+    fn time_in_state(&self) -> f64 {
+        0.0
+    }
+
+    /// 由`StateMachine`在回调前写入当前状态的停留时间
+    fn set_time_in_state(&mut self, _seconds: f64) {}
+}
+
+/// 状态回调返回的转换结果
+pub enum Transition<C: Context> {
+    /// 未处理该回调，交给父状态处理；所有状态都未处理时由转换表决定是否切换
+    Unhandled,
+    /// 已处理，保持当前状态，本次回调不检查转换表
+    Stay,
+    /// 切换到新状态（替换栈顶状态）
+    Switch(Box<dyn State<C>>),
+    /// 将新状态压入状态栈，当前状态暂停
+    Push(Box<dyn State<C>>),
+    /// 弹出栈顶状态，恢复下面的状态
+    Pop,
+}
+
+impl<C: Context> Transition<C> {
+    /// 构造切换到`state`的转换
+    pub fn switch(state: impl State<C>) -> Self {
+        Transition::Switch(Box::new(state))
+    }
+
+    /// 构造压入`state`的转换
+    pub fn push(state: impl State<C>) -> Self {
+        Transition::Push(Box::new(state))
+    }
+}
+
+/// 与引擎无关的状态。所有回调都通过上下文`C`访问外部环境。
+///
+/// 状态实例由状态机持有，回调以`&mut self`调用，因此状态可以保存自己的数据（计数器、冷却、连击段数等），
+/// 这些数据在状态保持活动期间跨帧保留。离开后再进入同一状态时会创建新的实例。
+pub trait State<C: Context>: Debug + Send + Sync + Any {
+    /// 状态名称，默认为类型名（不含模块路径）
+    fn name(&self) -> &str {
+        short_type_name(std::any::type_name::<Self>())
+    }
+
+    /// 父状态。子状态未处理的回调会冒泡到父状态。
+    ///
+    /// 状态机进入该状态时用它创建父状态实例；在共享同一父状态的子状态之间切换时，
+    /// 父状态实例（及其数据）会保留下来
+    fn parent(&self) -> Option<Box<dyn State<C>>> {
+        None
+    }
+
+    /// 虚拟功能。进入该状态时（更改活动状态后），由状态机器调用
+    fn init(&mut self, _ctx: &mut C) {}
+
+    /// 虚拟功能。离开该状态时，由状态机器调用，用于停止声音、恢复碰撞形状、取消计时器等
+    fn exit(&mut self, _ctx: &mut C) {}
+
+    /// 虚拟功能。该状态保持活动、而其子状态从`from`切换到`to`时调用（`from`已退出，`to`尚未初始化）
+    fn on_transition(&mut self, _ctx: &mut C, _from: &dyn State<C>, _to: &dyn State<C>) {}
+
+    /// 虚拟功能。有新状态压入到该状态之上时调用
+    fn pause(&mut self, _ctx: &mut C) {}
+
+    /// 虚拟功能。压在该状态之上的状态被弹出、该状态重新成为栈顶时调用（不会再次调用`init`）
+    fn resume(&mut self, _ctx: &mut C) {}
+
+    /// 虚拟函数。对应于`_ready()`回调：作为初始状态启动时在`init`之前调用，
+    /// 所有者就绪前已经设置的状态则在所有者就绪时调用
+    fn ready(&mut self, _ctx: &mut C) {}
+
+    /// 虚拟函数。对应`_input()`回调
+    fn input(&mut self, _ctx: &mut C, _event: &C::Input) -> Transition<C> {
+        Transition::Unhandled
+    }
+
+    /// 虚拟函数。处理通过`send_event`发送到状态机的自定义事件
+    fn event(&mut self, _ctx: &mut C, _event: &C::Event) -> Transition<C> {
+        Transition::Unhandled
+    }
+
+    /// 停留多少秒后触发`on_timeout`，`None`表示不计时（例如翻滚持续0.4秒）
+    fn timeout(&self) -> Option<f64> {
+        None
+    }
+
+    /// 虚拟函数。停留时间到达`timeout`后，在`_process()`中调用一次。
+    /// 每次进入状态（包括切换到自身和压入）重新计时，被压栈暂停期间不计时
+    fn on_timeout(&mut self, _ctx: &mut C) -> Transition<C> {
+        Transition::Unhandled
+    }
+
+    /// 虚拟函数。对应`_process()`回调
+    fn process(&mut self, _ctx: &mut C, _delta: f64) -> Transition<C> {
+        Transition::Unhandled
+    }
+
+    /// 虚拟函数。对应`_physics_process()`回调
+    fn physics_process(&mut self, _ctx: &mut C, _delta: f64) -> Transition<C> {
+        Transition::Unhandled
+    }
+
+    /// 虚拟函数。并行决策：只根据快照选择要切换到的状态（注册ID），不切换时返回`None`。
+    ///
+    /// 以`&self`在工作线程中调用，不能访问上下文；返回的状态由主线程在应用阶段切换。
+    /// 叶子状态返回`None`时继续询问父状态
+    fn decide(&self, _snapshot: &Snapshot) -> Option<String> {
+        None
+    }
+
+    /// 虚拟函数。对应`_integrate_forces()`回调
+    fn integrate_forces(&mut self, _ctx: &mut C, _delta: f64) {}
+}
+
+/// 去掉类型名中的模块路径，例如`crate::player::IdleState` -> `IdleState`
+fn short_type_name(full: &str) -> &str {
+    let base = full.split('<').next().unwrap_or(full);
+    match base.rfind("::") {
+        Some(index) => &full[index + 2..],
+        None => full,
+    }
+}
@@ -0,0 +1,305 @@
+use crate::fsm::registry::StateRegistry;
+use crate::fsm::state::Context;
+use std::fmt;
+
+/// 守卫条件
+pub type Guard<C> = Box<dyn Fn(&C) -> bool + Send + Sync>;
+
+/// 事件匹配器
+pub type EventMatcher<C> = Box<dyn Fn(&<C as Context>::Event) -> bool + Send + Sync>;
+
+/// 触发规则检查的回调
+pub enum Trigger<C: Context> {
+    /// `_process()`之后检查
+    Process,
+    /// `_physics_process()`之后检查
+    PhysicsProcess,
+    /// `_input()`之后检查
+    Input,
+    /// 收到匹配的自定义事件之后检查
+    Event(EventMatcher<C>),
+    /// `_process()`之后检查，当前状态停留至少指定秒数时匹配
+    After(f64),
+}
+
+impl<C: Context> Trigger<C> {
+    /// 触发器名称，用于调试和导出
+    pub fn name(&self) -> &'static str {
+        match self {
+            Trigger::Process => "process",
+            Trigger::PhysicsProcess => "physics_process",
+            Trigger::Input => "input",
+            Trigger::Event(_) => "event",
+            Trigger::After(_) => "after",
+        }
+    }
+
+    /// 是否与实际发生的回调匹配
+    fn matches(&self, ctx: &C, fired: &Fired<'_, C>) -> bool {
+        match (self, fired) {
+            (Trigger::Process, Fired::Process)
+            | (Trigger::PhysicsProcess, Fired::PhysicsProcess)
+            | (Trigger::Input, Fired::Input) => true,
+            (Trigger::Event(matcher), Fired::Event(event)) => matcher(event),
+            (Trigger::After(seconds), Fired::Process) => ctx.time_in_state() >= *seconds,
+            _ => false,
+        }
+    }
+}
+
+/// 实际发生的回调
+pub enum Fired<'a, C: Context> {
+    Process,
+    PhysicsProcess,
+    Input,
+    Event(&'a C::Event),
+    /// 不检查规则的回调（例如`_integrate_forces()`）
+    None,
+}
+
+impl<C: Context> Fired<'_, C> {
+    /// 回调名称
+    pub fn name(&self) -> &'static str {
+        match self {
+            Fired::Process => "process",
+            Fired::PhysicsProcess => "physics_process",
+            Fired::Input => "input",
+            Fired::Event(_) => "event",
+            Fired::None => "none",
+        }
+    }
+}
+
+/// 一条转换规则：在`from`状态中，`trigger`发生且`guard`成立时切换到`to`
+pub struct Rule<C: Context> {
+    from: Option<String>,
+    trigger: Trigger<C>,
+    guard: Option<Guard<C>>,
+    label: Option<String>,
+    to: String,
+}
+
+impl<C: Context> Rule<C> {
+    /// 创建规则。`from`和`to`都是注册ID，`from`为`None`时对任意状态生效
+    pub fn new(from: Option<&str>, trigger: Trigger<C>, to: &str) -> Self {
+        Self {
+            from: from.map(str::to_string),
+            trigger,
+            guard: None,
+            label: None,
+            to: to.to_string(),
+        }
+    }
+
+    /// 添加守卫条件，`label`用于调试和导出
+    pub fn when<G>(mut self, label: &str, guard: G) -> Self
+    where
+        G: Fn(&C) -> bool + Send + Sync + 'static,
+    {
+        self.guard = Some(Box::new(guard));
+        self.label = Some(label.to_string());
+        self
+    }
+
+    /// 设置标签而不添加守卫条件，用于说明`after`等无条件规则
+    pub fn labeled(mut self, label: &str) -> Self {
+        self.label = Some(label.to_string());
+        self
+    }
+
+    /// 源状态，`None`表示任意状态
+    pub fn from(&self) -> Option<&str> {
+        self.from.as_deref()
+    }
+
+    /// 触发器
+    pub fn trigger(&self) -> &Trigger<C> {
+        &self.trigger
+    }
+
+    /// 规则的标签：守卫条件的说明或`labeled`设置的标签
+    pub fn label(&self) -> Option<&str> {
+        self.label.as_deref()
+    }
+
+    /// 目标状态的注册ID
+    pub fn to(&self) -> &str {
+        &self.to
+    }
+}
+
+impl<C: Context> fmt::Debug for Rule<C> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Rule")
+            .field("from", &self.from)
+            .field("trigger", &self.trigger.name())
+            .field("label", &self.label)
+            .field("to", &self.to)
+            .finish()
+    }
+}
+
+/// 转换表校验错误
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TableError {
+    /// 初始状态没有注册
+    MissingInitial(String),
+    /// 规则的源状态没有注册
+    MissingSource { rule: usize, from: String },
+    /// 规则的目标状态没有注册
+    MissingTarget { rule: usize, to: String },
+    /// 声明的入口状态没有注册
+    MissingEntry(String),
+    /// 从初始状态出发无法到达的状态
+    Unreachable(String),
+}
+
+impl fmt::Display for TableError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TableError::MissingInitial(id) => write!(f, "初始状态 {} 未注册", id),
+            TableError::MissingSource { rule, from } => {
+                write!(f, "规则 #{} 的源状态 {} 未注册", rule, from)
+            }
+            TableError::MissingTarget { rule, to } => {
+                write!(f, "规则 #{} 的目标状态 {} 未注册", rule, to)
+            }
+            TableError::MissingEntry(id) => write!(f, "入口状态 {} 未注册", id),
+            TableError::Unreachable(id) => write!(f, "状态 {} 无法从初始状态到达", id),
+        }
+    }
+}
+
+/// 声明式转换表：状态注册表（节点）加上转换规则（边），由状态机自己求值。
+///
+/// 状态回调没有切换状态时，状态机按声明顺序检查规则，第一条匹配的规则生效。
+/// 目标就是当前状态的规则不会引起切换。
+///
+/// 只由代码压入（`push`）的状态没有指向它们的规则，可以用`add_entry`声明为入口，校验时视为可到达。
+#[derive(Debug)]
+pub struct TransitionTable<C: Context> {
+    registry: StateRegistry<C>,
+    rules: Vec<Rule<C>>,
+    entries: Vec<String>,
+}
+
+impl<C: Context> TransitionTable<C> {
+    /// 基于状态注册表创建一个没有规则的转换表
+    pub fn new(registry: StateRegistry<C>) -> Self {
+        Self {
+            registry,
+            rules: Vec::new(),
+            entries: Vec::new(),
+        }
+    }
+
+    /// 添加规则
+    pub fn add(&mut self, rule: Rule<C>) -> &mut Self {
+        self.rules.push(rule);
+        self
+    }
+
+    /// 声明入口状态：由代码直接进入（例如压入的覆盖层），不需要规则就能到达
+    pub fn add_entry(&mut self, id: &str) -> &mut Self {
+        if !self.entries.iter().any(|entry| entry == id) {
+            self.entries.push(id.to_string());
+        }
+        self
+    }
+
+    /// 所有规则，按声明顺序
+    pub fn rules(&self) -> &[Rule<C>] {
+        &self.rules
+    }
+
+    /// 声明的入口状态
+    pub fn entries(&self) -> &[String] {
+        &self.entries
+    }
+
+    /// 状态注册表
+    pub fn registry(&self) -> &StateRegistry<C> {
+        &self.registry
+    }
+
+    /// 状态注册表（可变）
+    pub fn registry_mut(&mut self) -> &mut StateRegistry<C> {
+        &mut self.registry
+    }
+
+    /// 求值：返回第一条匹配规则的目标ID。
+    ///
+    /// `current`是当前状态的注册ID。当前状态不是注册的状态时为`None`，
+    /// 此时任何规则都不匹配，`from`为`None`的规则也只对注册的状态生效
+    pub fn evaluate(&self, ctx: &C, current: Option<&str>, fired: &Fired<'_, C>) -> Option<&str> {
+        let current = current.filter(|id| self.registry.contains(id))?;
+
+        self.rules
+            .iter()
+            .filter(|rule| rule.from.as_deref().is_none_or(|from| from == current))
+            .filter(|rule| rule.trigger.matches(ctx, fired))
+            .find(|rule| rule.guard.as_ref().is_none_or(|guard| guard(ctx)))
+            .map(|rule| rule.to.as_str())
+            .filter(|to| *to != current)
+    }
+
+    /// 校验转换表：所有引用的状态都已注册，所有注册的状态都能从`initial`或入口状态到达
+    pub fn validate(&self, initial: &str) -> Result<(), Vec<TableError>> {
+        let mut errors = Vec::new();
+
+        if !self.registry.contains(initial) {
+            errors.push(TableError::MissingInitial(initial.to_string()));
+        }
+        for (index, rule) in self.rules.iter().enumerate() {
+            if let Some(from) = rule.from.as_deref()
+                && !self.registry.contains(from)
+            {
+                errors.push(TableError::MissingSource {
+                    rule: index,
+                    from: from.to_string(),
+                });
+            }
+            if !self.registry.contains(&rule.to) {
+                errors.push(TableError::MissingTarget {
+                    rule: index,
+                    to: rule.to.clone(),
+                });
+            }
+        }
+        for entry in &self.entries {
+            if !self.registry.contains(entry) {
+                errors.push(TableError::MissingEntry(entry.clone()));
+            }
+        }
+
+        // 从初始状态和入口状态出发做广度优先搜索
+        let mut reachable = vec![initial];
+        for entry in &self.entries {
+            if !reachable.contains(&entry.as_str()) {
+                reachable.push(entry);
+            }
+        }
+        let mut index = 0;
+        while index < reachable.len() {
+            let current = reachable[index];
+            for rule in &self.rules {
+                let applies = rule.from.as_deref().is_none_or(|from| from == current);
+                if applies && !reachable.contains(&rule.to.as_str()) {
+                    reachable.push(&rule.to);
+                }
+            }
+            index += 1;
+        }
+        for id in self.registry.ids() {
+            if !reachable.contains(&id) {
+                errors.push(TableError::Unreachable(id.to_string()));
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+}
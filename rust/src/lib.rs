@@ -1,4 +1,9 @@
+// gdext 0.2 的`#[derive(GodotClass)]`为导出属性生成的闭包会触发该lint
+#![allow(clippy::result_large_err)]
+
+pub mod fsm;
 pub mod player;
+pub mod rigid_body;
 pub mod utils;
 
 use godot::prelude::*;
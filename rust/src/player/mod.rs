@@ -1,3 +1,5 @@
+#[allow(clippy::module_inception)]
 pub mod player;
+pub mod player_event;
 pub mod player_state_machine;
 mod states_impl;
@@ -1,8 +1,6 @@
 use crate::player::player_state_machine::PlayerStateMachine;
-use crate::utils::character_state_common::{CharacterResource};
-use godot::classes::{
-    AnimatedSprite2D, AnimationPlayer, CharacterBody2D, ICharacterBody2D, Input, InputEvent,
-};
+use crate::utils::character_state_common::CharacterResource;
+use godot::classes::{AnimatedSprite2D, AnimationPlayer, CharacterBody2D, ICharacterBody2D, Input};
 use godot::prelude::*;
 
 #[derive(GodotClass)]
@@ -12,7 +10,7 @@ pub struct Player {
     speed: f64,
     animated_sprite: Gd<AnimatedSprite2D>,
     pub(crate) animation_player: Gd<AnimationPlayer>,
-    pub(crate) state_machine: Gd<PlayerStateMachine>,
+    pub(crate) state_machine: Option<Gd<PlayerStateMachine>>,
     resource: Gd<CharacterResource>,
     base: Base<CharacterBody2D>,
 }
@@ -27,22 +25,14 @@ impl ICharacterBody2D for Player {
             speed: 50.0,
             animated_sprite: AnimatedSprite2D::new_alloc(),
             animation_player: AnimationPlayer::new_alloc(),
-            state_machine: PlayerStateMachine::new_alloc(),
+            state_machine: None,
             resource,
             base,
         }
     }
 
-    fn process(&mut self, delta: f64) {
-        // 处理非物理逻辑（动画、状态机）
-        self.state_machine.bind_mut().handle_process(delta);
-    }
-
-    fn physics_process(&mut self, delta: f64) {
-        // 物理相关逻辑
-        self.state_machine.bind_mut().handle_physics_process(delta);
-
-        // 执行移动
+    fn physics_process(&mut self, _delta: f64) {
+        // 执行移动（状态机节点自己接收物理回调，已经在此之前设置好本帧的速度）
         self.base_mut().move_and_slide();
 
         // 保持人物朝向
@@ -61,31 +51,22 @@ impl ICharacterBody2D for Player {
         self.animation_player = self.base().get_node_as("AnimationPlayer");
 
         // 获取状态机节点
+        // 子节点先于父节点就绪：状态机此时已经准备好资源（AnimationPlayer、速度）并进入初始状态，
+        // 这里只共享状态机的资源，不再重复设置
         if self.base().has_node("PlayerStateMachine") {
-            self.state_machine = self.base().get_node_as("PlayerStateMachine");
-
-            // 设置资源
-            self.resource
-                .bind_mut()
-                .set_animation_player(self.animation_player.clone());
-            self.resource.bind_mut().set_speed(self.speed);
-
-            // 更新状态机资源
-
-            self.state_machine
-                .bind_mut()
-                .set_resource(self.resource.clone());
+            let mut state_machine: Gd<PlayerStateMachine> =
+                self.base().get_node_as("PlayerStateMachine");
+            self.resource = state_machine.bind().resource().clone();
+            // 子节点的物理回调默认在父节点之后，提前到move_and_slide之前；
+            // 注册到StateMachineManager后由管理器代为调用，管理器的优先级同样在Player之前
+            state_machine.set_physics_process_priority(-1);
+            self.state_machine = Some(state_machine);
         } else {
-            godot_warn!(
+            godot_error!(
                 "PlayerStateMachine节点未找到，请确保在Player节点下添加了PlayerStateMachine子节点"
             );
         }
     }
-
-    fn input(&mut self, event: Gd<InputEvent>) {
-        // 处理输入事件
-        self.state_machine.bind_mut().handle_input(event);
-    }
 }
 
 #[godot_api]
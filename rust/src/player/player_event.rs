@@ -0,0 +1,58 @@
+use crate::utils::state_machine::GodotEvent;
+use godot::prelude::*;
+
+/// 玩家状态机接受的自定义游戏事件
+#[derive(Debug, Clone)]
+pub enum PlayerEvent {
+    /// 受到伤害
+    DamageTaken { amount: f64 },
+    /// 拾取物品
+    ItemPickedUp { item: String },
+    /// 对话结束
+    DialogueFinished,
+    /// 进入区域
+    AreaEntered { area: String },
+    /// 其他由GDScript发送、Rust中没有对应类型的事件
+    Custom { name: String, payload: Variant },
+}
+
+impl PlayerEvent {
+    /// 从GDScript传入的事件名和参数构造事件，参数类型不匹配时作为`Custom`事件
+    pub fn from_variant(name: &str, payload: &Variant) -> Self {
+        let event = match name {
+            "damage_taken" => payload
+                .try_to::<f64>()
+                .ok()
+                .map(|amount| PlayerEvent::DamageTaken { amount }),
+            "item_picked_up" => {
+                payload
+                    .try_to::<GString>()
+                    .ok()
+                    .map(|item| PlayerEvent::ItemPickedUp {
+                        item: item.to_string(),
+                    })
+            }
+            "dialogue_finished" => Some(PlayerEvent::DialogueFinished),
+            "area_entered" => {
+                payload
+                    .try_to::<GString>()
+                    .ok()
+                    .map(|area| PlayerEvent::AreaEntered {
+                        area: area.to_string(),
+                    })
+            }
+            _ => None,
+        };
+
+        event.unwrap_or_else(|| PlayerEvent::Custom {
+            name: name.to_string(),
+            payload: payload.clone(),
+        })
+    }
+}
+
+impl GodotEvent for PlayerEvent {
+    fn from_variant(name: &str, payload: &Variant) -> Self {
+        PlayerEvent::from_variant(name, payload)
+    }
+}
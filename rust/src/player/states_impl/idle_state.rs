@@ -1,31 +1,51 @@
-use crate::player::states_impl::run_state::{BackRunState, RunState, SideRunState};
-use crate::utils::character_state_common::{
-    CharacterResource, CharacterStateCommon, DirectionType, determine_direction_type,
-    get_input_direction,
-};
-use crate::utils::state_machine::{GodotInitialState, GodotState, GodotStateTraits};
-use godot::builtin::Vector2;
-use godot::classes::{CharacterBody2D, INode};
-use godot::obj::{Base, Gd};
-use godot::prelude::{GodotClass, godot_api};
+use crate::fsm::state::State;
+use crate::player::states_impl::locomotion_state::LocomotionState;
+use crate::utils::character_state_common::{CharacterContext, CharacterStateCommon};
+use crate::utils::state_machine::GodotInitialState;
+use crate::utils::state_node::StateNode;
+use godot::prelude::{GString, GodotClass, StringName};
+
+/// 空闲状态
+#[derive(Debug, Default)]
+pub struct IdleState {
+    /// 播放的动画，为空时按朝向选择（`idle`、`back_idle`等）
+    animation_name: String,
+    /// 由状态节点或定义文件创建时为状态ID
+    name: String,
+}
 
+/// 空闲状态节点，作为`PlayerStateMachine`的子节点添加后可以在编辑器中调整参数
 #[derive(GodotClass, Debug)]
-#[class(base=Node)]
-pub struct IdleState;
+#[class(init, base=Node, rename=IdleState)]
+pub struct IdleStateNode {
+    /// 播放的动画，为空时按朝向选择
+    #[export]
+    animation_name: GString,
+    /// 停留`duration`秒后自动切换到的状态ID，为空时不自动切换
+    #[export]
+    next_state: StringName,
+    #[export]
+    duration: f64,
+}
 
-#[godot_api]
-impl INode for IdleState {
-    fn init(_base: Base<Self::Base>) -> Self {
-        IdleState
+impl IdleState {
+    /// 以`name`作为状态名称创建，`animation_name`为空时按朝向选择动画
+    pub fn new(name: &str, animation_name: &str) -> Self {
+        Self {
+            animation_name: animation_name.to_string(),
+            name: name.to_string(),
+        }
     }
 }
 
 // 实现初始状态标记特性
-impl GodotInitialState for IdleState {}
+impl<C: CharacterContext> GodotInitialState<C> for IdleState {}
 
 impl CharacterStateCommon for IdleState {
     fn get_animation_name(&self, animation_direction: &str) -> String {
-        if animation_direction != "default" {
+        if !self.animation_name.is_empty() {
+            self.animation_name.clone()
+        } else if animation_direction != "default" {
             format!("{}_idle", animation_direction)
         } else {
             "idle".to_string()
@@ -33,137 +53,30 @@ impl CharacterStateCommon for IdleState {
     }
 }
 
-impl GodotState for IdleState {
-    type Owner = CharacterBody2D;
-    type Resource = CharacterResource;
-
-    fn init(&self, owner: &mut Gd<CharacterBody2D>, resource: &mut Gd<CharacterResource>) {
-        // 确保角色停止移动
-        owner.set_velocity(Vector2::ZERO);
-
-        // 播放对应的动画
-        let animation_name = self.get_animation_name(resource.bind().get_animation_direction());
-        resource.bind_mut().play_animation(&animation_name);
+impl<C: CharacterContext> StateNode<C> for IdleStateNode {
+    fn create_state(&self, id: &str) -> Box<dyn State<C>> {
+        Box::new(IdleState::new(id, &self.animation_name.to_string()))
     }
 
-    fn process(
-        &self,
-        _owner: &mut Gd<CharacterBody2D>,
-        _resource: &mut Gd<CharacterResource>,
-        _delta: f64,
-    ) -> Option<Box<dyn GodotStateTraits<Owner = CharacterBody2D, Resource = CharacterResource>>>
-    {
-        let direction = get_input_direction();
-
-        if !direction.is_zero_approx() {
-            // 根据方向类型选择适当的状态
-            let direction_type = determine_direction_type(direction);
-
-            return match direction_type {
-                DirectionType::Back => Some(Box::new(BackRunState)),
-                DirectionType::Side => Some(Box::new(SideRunState)),
-                DirectionType::Default => Some(Box::new(RunState)),
-            };
-        }
-
-        None
+    fn next_state(&self) -> Option<(String, f64)> {
+        (!self.next_state.is_empty()).then(|| (self.next_state.to_string(), self.duration))
     }
 }
 
-// 后向空闲状态
-#[derive(Debug)]
-pub struct BackIdleState;
-
-impl CharacterStateCommon for BackIdleState {
-    fn get_animation_name(&self, _animation_direction: &str) -> String {
-        "back_idle".to_string()
-    }
-}
-
-impl GodotInitialState for BackIdleState {}
-
-impl GodotState for BackIdleState {
-    type Owner = CharacterBody2D;
-    type Resource = CharacterResource;
-
-    fn init(&self, owner: &mut Gd<CharacterBody2D>, resource: &mut Gd<CharacterResource>) {
-        // 确保角色停止移动
-        owner.set_velocity(Vector2::ZERO);
-
-        // 播放对应的动画
-        let animation_name = self.get_animation_name(resource.bind().get_animation_direction());
-        resource.bind_mut().play_animation(&animation_name);
-    }
-
-    fn process(
-        &self,
-        _owner: &mut Gd<CharacterBody2D>,
-        _resource: &mut Gd<CharacterResource>,
-        _delta: f64,
-    ) -> Option<Box<dyn GodotStateTraits<Owner = CharacterBody2D, Resource = CharacterResource>>>
-    {
-        let direction = get_input_direction();
-
-        if !direction.is_zero_approx() {
-            // 根据方向类型选择适当的状态
-            let direction_type = determine_direction_type(direction);
-
-            return match direction_type {
-                DirectionType::Back => Some(Box::new(BackRunState)),
-                DirectionType::Side => Some(Box::new(SideRunState)),
-                DirectionType::Default => Some(Box::new(RunState)),
-            };
+impl<C: CharacterContext> State<C> for IdleState {
+    fn name(&self) -> &str {
+        if self.name.is_empty() {
+            "IdleState"
+        } else {
+            &self.name
         }
-
-        None
     }
-}
-
-// 侧向空闲状态
-#[derive(Debug)]
-pub struct SideIdleState;
 
-impl CharacterStateCommon for SideIdleState {
-    fn get_animation_name(&self, _animation_direction: &str) -> String {
-        "side_idle".to_string()
+    fn parent(&self) -> Option<Box<dyn State<C>>> {
+        Some(Box::new(LocomotionState))
     }
-}
-
-impl GodotInitialState for SideIdleState {}
-
-impl GodotState for SideIdleState {
-    type Owner = CharacterBody2D;
-    type Resource = CharacterResource;
-
-    fn init(&self, owner: &mut Gd<CharacterBody2D>, resource: &mut Gd<CharacterResource>) {
-        // 确保角色停止移动
-        owner.set_velocity(Vector2::ZERO);
-
-        // 播放对应的动画
-        let animation_name = self.get_animation_name(resource.bind().get_animation_direction());
-        resource.bind_mut().play_animation(&animation_name);
-    }
-
-    fn process(
-        &self,
-        _owner: &mut Gd<CharacterBody2D>,
-        _resource: &mut Gd<CharacterResource>,
-        _delta: f64,
-    ) -> Option<Box<dyn GodotStateTraits<Owner = CharacterBody2D, Resource = CharacterResource>>>
-    {
-        let direction = get_input_direction();
-
-        if !direction.is_zero_approx() {
-            // 根据方向类型选择适当的状态
-            let direction_type = determine_direction_type(direction);
-
-            return match direction_type {
-                DirectionType::Back => Some(Box::new(BackRunState)),
-                DirectionType::Side => Some(Box::new(SideRunState)),
-                DirectionType::Default => Some(Box::new(RunState)),
-            };
-        }
 
-        None
+    fn init(&mut self, ctx: &mut C) {
+        LocomotionState::enter_idle(self, ctx);
     }
 }
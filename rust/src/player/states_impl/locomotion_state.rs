@@ -0,0 +1,218 @@
+use crate::fsm::decision::Snapshot;
+use crate::fsm::state::State;
+use crate::player::states_impl::registry::LOCOMOTION_STATES;
+use crate::utils::character_state_common::{
+    CharacterContext, CharacterResource, CharacterStateCommon, DirectionType, INPUT_DIRECTION_KEY,
+    determine_direction_type,
+};
+use godot::builtin::Vector2;
+
+/// 移动父状态：提供子状态共用的行为和转换条件，子状态只决定动画和速度
+#[derive(Debug)]
+pub struct LocomotionState;
+
+impl LocomotionState {
+    /// 没有输入，且上一次奔跑的朝向为`facing`
+    pub fn is_idle_facing<C: CharacterContext>(ctx: &C, facing: DirectionType) -> bool {
+        ctx.input_direction().is_zero_approx()
+            && DirectionType::from_animation_direction(&ctx.animation_direction()) == facing
+    }
+
+    /// 有输入，且输入方向属于`direction`
+    pub fn is_running_towards<C: CharacterContext>(ctx: &C, direction: DirectionType) -> bool {
+        let input = ctx.input_direction();
+        !input.is_zero_approx() && determine_direction_type(input) == direction
+    }
+
+    /// 与转换表的移动规则相同：没有输入时按朝向选择空闲状态，否则按输入方向选择奔跑状态，返回状态ID
+    pub fn target(input: Vector2, facing: DirectionType) -> &'static str {
+        if input.is_zero_approx() {
+            match facing {
+                DirectionType::Default => "idle",
+                DirectionType::Back => "back_idle",
+                DirectionType::Side => "side_idle",
+            }
+        } else {
+            match determine_direction_type(input) {
+                DirectionType::Default => "run",
+                DirectionType::Back => "back_run",
+                DirectionType::Side => "side_run",
+            }
+        }
+    }
+
+    /// 子状态共用：停止移动并播放空闲动画
+    pub fn enter_idle<C: CharacterContext>(state: &impl CharacterStateCommon, ctx: &mut C) {
+        // 确保角色停止移动
+        ctx.set_velocity(Vector2::ZERO);
+
+        // 播放对应的动画
+        let animation_name = state.get_animation_name(&ctx.animation_direction());
+        ctx.play_animation(&animation_name);
+    }
+
+    /// 子状态共用：设置朝向并播放奔跑动画
+    pub fn enter_run<C: CharacterContext>(state: &impl CharacterStateCommon, ctx: &mut C) {
+        let animation_direction = state.get_direction_type().animation_direction();
+        ctx.set_animation_direction(animation_direction);
+        let animation_name = state.get_animation_name(animation_direction);
+        ctx.play_animation(&animation_name);
+    }
+
+    /// 子状态共用：按状态的速度倍率更新速度
+    pub fn apply_velocity<C: CharacterContext>(state: &impl CharacterStateCommon, ctx: &mut C) {
+        let speed = ctx.speed() * state.get_speed_multiplier();
+        let velocity = ctx.input_direction() * speed as f32;
+        ctx.set_velocity(velocity);
+    }
+}
+
+// 状态之间的切换由转换表（`player_transition_table`）声明，父状态本身不处理回调；
+// 开启并行决策时由`decide`在工作线程中按快照（`character_snapshot`）选择同样的状态，
+// 与移动规则一样只从移动状态出发，以移动状态为父状态的状态节点不受影响
+impl<C: CharacterContext> State<C> for LocomotionState {
+    fn decide(&self, snapshot: &Snapshot) -> Option<String> {
+        if !LOCOMOTION_STATES.contains(&snapshot.state_id()?) {
+            return None;
+        }
+        let (x, y) = snapshot.get_vector(INPUT_DIRECTION_KEY)?;
+        let facing = snapshot
+            .get_str(CharacterResource::ANIMATION_DIRECTION.name())
+            .map_or(
+                DirectionType::Default,
+                DirectionType::from_animation_direction,
+            );
+        Some(Self::target(Vector2::new(x, y), facing).to_string())
+    }
+}
+
+/// 声明移动子状态和玩家的状态注册表，省去每个状态重复的特性实现。只在本crate中使用，
+/// 生成的状态依赖`CharacterContext`和`LocomotionState`，通用的状态机节点见`godot_state_machine!`。
+///
+/// 注册表列出所有可以按ID切换的状态（需要实现`Default`），之后的每个状态只需要声明类型、动画、朝向和速度倍率：
+/// `idle`状态进入时停止移动并播放动画；`run`状态进入时设置朝向并播放动画，在物理帧按输入移动。
+/// 生成的状态以`LocomotionState`为父状态，实现`Debug`（状态名称即类型名）、`Default`和`GodotInitialState`。
+///
+/// 参数之后的`callbacks`块可以写其他`State`回调（例如`timeout`/`on_timeout`、`event`），它们会加入生成的特性实现；
+/// 类型已经生成的回调（`idle`的`init`，`run`的`init`和`physics_process`）不能重复声明。
+/// 需要运行时参数的状态（例如`IdleState`、`RunState`及其状态节点）仍然手写。
+///
+/// ```ignore
+/// locomotion_states! {
+///     /// 玩家可以按名称切换到的状态
+///     pub fn player_state_registry {
+///         "idle" => IdleState,
+///         "walk" => WalkState,
+///     }
+///
+///     /// 行走状态
+///     run WalkState {
+///         animation: "walking",
+///         direction: Default,
+///         speed_multiplier: 0.5,
+///     }
+///
+///     /// 冲刺0.4秒后回到空闲
+///     run DashState {
+///         animation: "dash",
+///         direction: Side,
+///         speed_multiplier: 3.0,
+///         callbacks {
+///             fn timeout(&self) -> Option<f64> {
+///                 Some(0.4)
+///             }
+///
+///             fn on_timeout(&mut self, _ctx: &mut C) -> Transition<C> {
+///                 Transition::switch(IdleState::default())
+///             }
+///         }
+///     }
+/// }
+/// ```
+macro_rules! locomotion_states {
+    (
+        $(#[$registry_meta:meta])*
+        $vis:vis fn $registry:ident {
+            $($id:literal => $registered:ty),* $(,)?
+        }
+        $(
+            $(#[$meta:meta])*
+            $kind:ident $state:ident {
+                animation: $animation:expr,
+                direction: $direction:ident,
+                $(speed_multiplier: $speed:expr,)?
+                $(callbacks {
+                    $($callback:item)*
+                })?
+            }
+        )*
+    ) => {
+        $(#[$registry_meta])*
+        $vis fn $registry<C: $crate::utils::character_state_common::CharacterContext>(
+        ) -> $crate::fsm::registry::StateRegistry<C> {
+            let mut registry = $crate::fsm::registry::StateRegistry::new();
+            $(registry.register_default::<$registered>($id);)*
+            registry
+        }
+
+        $(
+            $(#[$meta])*
+            #[derive(Debug, Default)]
+            pub struct $state;
+
+            impl $crate::utils::character_state_common::CharacterStateCommon for $state {
+                fn get_animation_name(&self, _animation_direction: &str) -> String {
+                    $animation.to_string()
+                }
+
+                fn get_direction_type(&self) -> $crate::utils::character_state_common::DirectionType {
+                    $crate::utils::character_state_common::DirectionType::$direction
+                }
+
+                $(
+                    fn get_speed_multiplier(&self) -> f64 {
+                        $speed
+                    }
+                )?
+            }
+
+            impl<C: $crate::utils::character_state_common::CharacterContext>
+                $crate::utils::state_machine::GodotInitialState<C> for $state
+            {
+            }
+
+            impl<C: $crate::utils::character_state_common::CharacterContext>
+                $crate::fsm::state::State<C> for $state
+            {
+                fn parent(&self) -> Option<Box<dyn $crate::fsm::state::State<C>>> {
+                    Some(Box::new(
+                        $crate::player::states_impl::locomotion_state::LocomotionState,
+                    ))
+                }
+
+                $crate::player::states_impl::locomotion_state::locomotion_states!(@callbacks $kind);
+
+                $($($callback)*)?
+            }
+        )*
+    };
+
+    (@callbacks idle) => {
+        fn init(&mut self, ctx: &mut C) {
+            $crate::player::states_impl::locomotion_state::LocomotionState::enter_idle(self, ctx);
+        }
+    };
+
+    (@callbacks run) => {
+        fn init(&mut self, ctx: &mut C) {
+            $crate::player::states_impl::locomotion_state::LocomotionState::enter_run(self, ctx);
+        }
+
+        fn physics_process(&mut self, ctx: &mut C, _delta: f64) -> $crate::fsm::state::Transition<C> {
+            $crate::player::states_impl::locomotion_state::LocomotionState::apply_velocity(self, ctx);
+            $crate::fsm::state::Transition::Unhandled
+        }
+    };
+}
+
+pub(crate) use locomotion_states;
@@ -1,2 +1,4 @@
 pub mod idle_state;
+pub mod locomotion_state;
+pub mod registry;
 pub mod run_state;
\ No newline at end of file
@@ -0,0 +1,500 @@
+use crate::fsm::definition::{DefinitionError, DefinitionLoader, Field};
+use crate::fsm::registry::StateFactory;
+use crate::fsm::table::{Rule, TransitionTable, Trigger};
+use crate::player::states_impl::idle_state::IdleState;
+use crate::player::states_impl::locomotion_state::{LocomotionState, locomotion_states};
+use crate::player::states_impl::run_state::{RUN_SPEED_MULTIPLIER, RunState};
+use crate::utils::character_state_common::{CharacterContext, DirectionType};
+
+locomotion_states! {
+    /// 玩家可以按名称切换到的状态
+    pub fn player_state_registry {
+        "idle" => IdleState,
+        "back_idle" => BackIdleState,
+        "side_idle" => SideIdleState,
+        "run" => RunState,
+        "back_run" => BackRunState,
+        "side_run" => SideRunState,
+    }
+
+    /// 后向空闲状态
+    idle BackIdleState {
+        animation: "back_idle",
+        direction: Back,
+    }
+
+    /// 侧向空闲状态
+    idle SideIdleState {
+        animation: "side_idle",
+        direction: Side,
+    }
+
+    /// 后向奔跑状态
+    run BackRunState {
+        animation: "back_running",
+        direction: Back,
+        speed_multiplier: RUN_SPEED_MULTIPLIER,
+    }
+
+    /// 侧向奔跑状态
+    run SideRunState {
+        animation: "side_running",
+        direction: Side,
+        speed_multiplier: RUN_SPEED_MULTIPLIER,
+    }
+}
+
+/// 移动规则：目标状态、条件说明和条件
+type LocomotionRule<C> = (&'static str, &'static str, fn(&C) -> bool);
+
+/// 移动状态的注册ID。移动规则只在这些状态之间切换，状态节点和压入的状态不受影响
+pub const LOCOMOTION_STATES: [&str; 6] = [
+    "idle",
+    "back_idle",
+    "side_idle",
+    "run",
+    "back_run",
+    "side_run",
+];
+
+/// 玩家的转换表：移动相关的六个状态之间的切换全部由输入方向决定。
+///
+/// 每条规则在`_process()`和`_physics_process()`之后都检查，并且只从移动状态出发，
+/// 规则的顺序与`player_machine.json`相同
+pub fn player_transition_table<C: CharacterContext>() -> TransitionTable<C> {
+    let mut table = TransitionTable::new(player_state_registry());
+    let rules: [LocomotionRule<C>; 6] = [
+        ("idle", "无输入，朝向正面", |ctx| {
+            LocomotionState::is_idle_facing(ctx, DirectionType::Default)
+        }),
+        ("back_idle", "无输入，朝向背面", |ctx| {
+            LocomotionState::is_idle_facing(ctx, DirectionType::Back)
+        }),
+        ("side_idle", "无输入，朝向侧面", |ctx| {
+            LocomotionState::is_idle_facing(ctx, DirectionType::Side)
+        }),
+        ("run", "向正面移动", |ctx| {
+            LocomotionState::is_running_towards(ctx, DirectionType::Default)
+        }),
+        ("back_run", "向背面移动", |ctx| {
+            LocomotionState::is_running_towards(ctx, DirectionType::Back)
+        }),
+        ("side_run", "向侧面移动", |ctx| {
+            LocomotionState::is_running_towards(ctx, DirectionType::Side)
+        }),
+    ];
+    let triggers: [fn() -> Trigger<C>; 2] = [|| Trigger::Process, || Trigger::PhysicsProcess];
+    for (to, label, guard) in rules {
+        for from in LOCOMOTION_STATES {
+            for trigger in triggers {
+                table.add(Rule::new(Some(from), trigger(), to).when(label, guard));
+            }
+        }
+    }
+    table
+}
+
+/// 角色定义文件可以使用的状态类型和条件，见`DefinitionLoader`。
+///
+/// 状态类型：
+/// - `idle`：停止移动并播放空闲动画，参数`animation`（为空时按朝向选择）
+/// - `run`：按输入方向移动，参数`animation`、`direction`（`default`/`back`/`side`）、`speed_multiplier`
+///
+/// 条件：`idle_facing`（无输入且朝向为指定方向）、`moving_towards`（向指定方向移动）
+pub fn character_definition_loader<C: CharacterContext>() -> DefinitionLoader<C> {
+    let mut loader = DefinitionLoader::new();
+    loader
+        .add_state_kind("idle", idle_kind)
+        .add_state_kind("run", run_kind)
+        .add_condition("idle_facing", |argument| {
+            let facing = direction_argument(argument)?;
+            Ok(Box::new(move |ctx| {
+                LocomotionState::is_idle_facing(ctx, facing)
+            }))
+        })
+        .add_condition("moving_towards", |argument| {
+            let direction = direction_argument(argument)?;
+            Ok(Box::new(move |ctx| {
+                LocomotionState::is_running_towards(ctx, direction)
+            }))
+        });
+    loader
+}
+
+fn idle_kind<C: CharacterContext>(
+    id: &str,
+    params: &Field<'_>,
+) -> Result<StateFactory<C>, DefinitionError> {
+    params.expect_keys(&["id", "kind", "animation"])?;
+    let id = id.to_string();
+    let animation = params.str_or("animation", "")?.to_string();
+    Ok(Box::new(move || Box::new(IdleState::new(&id, &animation))))
+}
+
+fn run_kind<C: CharacterContext>(
+    id: &str,
+    params: &Field<'_>,
+) -> Result<StateFactory<C>, DefinitionError> {
+    params.expect_keys(&["id", "kind", "animation", "direction", "speed_multiplier"])?;
+    let id = id.to_string();
+    let animation = params.str_or("animation", "")?.to_string();
+    let direction = match params.get("direction") {
+        Some(argument) => direction_argument(&argument)?,
+        None => DirectionType::Default,
+    };
+    let speed_multiplier = params.f64_or("speed_multiplier", RUN_SPEED_MULTIPLIER)?;
+    Ok(Box::new(move || {
+        Box::new(RunState::new(&id, &animation, direction, speed_multiplier))
+    }))
+}
+
+// 方向参数只接受`default`、`back`、`side`
+fn direction_argument(argument: &Field<'_>) -> Result<DirectionType, DefinitionError> {
+    match argument.as_str()? {
+        "default" => Ok(DirectionType::Default),
+        "back" => Ok(DirectionType::Back),
+        "side" => Ok(DirectionType::Side),
+        other => Err(argument.error(format!(
+            "未知的方向 {}，可用的方向：default, back, side",
+            other
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fsm::decision::{Decide, Snapshot, SnapshotValue};
+    use crate::fsm::machine::StateMachine;
+    use crate::fsm::state::{Context, Transition};
+    use crate::utils::character_state_common::{CharacterResource, INPUT_DIRECTION_KEY};
+    use crate::utils::state_node::add_node_state;
+    use godot::builtin::Vector2;
+
+    /// 不依赖引擎的角色：输入由测试设置，记录速度和播放的动画
+    #[derive(Debug)]
+    struct MockCharacter {
+        input: Vector2,
+        velocity: Vector2,
+        animation_direction: String,
+        animations: Vec<String>,
+        time_in_state: f64,
+    }
+
+    impl Default for MockCharacter {
+        fn default() -> Self {
+            Self {
+                input: Vector2::ZERO,
+                velocity: Vector2::ZERO,
+                animation_direction: "default".to_string(),
+                animations: Vec::new(),
+                time_in_state: 0.0,
+            }
+        }
+    }
+
+    impl Context for MockCharacter {
+        type Input = ();
+        type Event = ();
+
+        fn time_in_state(&self) -> f64 {
+            self.time_in_state
+        }
+
+        fn set_time_in_state(&mut self, seconds: f64) {
+            self.time_in_state = seconds;
+        }
+    }
+
+    impl CharacterContext for MockCharacter {
+        fn input_direction(&self) -> Vector2 {
+            self.input
+        }
+
+        fn set_velocity(&mut self, velocity: Vector2) {
+            self.velocity = velocity;
+        }
+
+        fn speed(&self) -> f64 {
+            100.0
+        }
+
+        fn animation_direction(&self) -> String {
+            self.animation_direction.clone()
+        }
+
+        fn set_animation_direction(&mut self, direction: &str) {
+            self.animation_direction = direction.to_string();
+        }
+
+        fn play_animation(&mut self, animation_name: &str) {
+            self.animations.push(animation_name.to_string());
+        }
+    }
+
+    #[test]
+    fn input_drives_the_six_locomotion_states() {
+        let mut machine = StateMachine::new();
+        machine.set_table(player_transition_table());
+        let mut character = MockCharacter::default();
+        let idle = machine
+            .table()
+            .and_then(|table| table.registry().create("idle"));
+        machine.start(&mut character, idle.expect("idle已注册"));
+
+        let steps = [
+            (Vector2::new(0.0, 1.0), "RunState", "running"),
+            (Vector2::new(0.0, -1.0), "BackRunState", "back_running"),
+            (Vector2::ZERO, "BackIdleState", "back_idle"),
+            (Vector2::new(1.0, 0.0), "SideRunState", "side_running"),
+            (Vector2::ZERO, "SideIdleState", "side_idle"),
+            (Vector2::new(-1.0, 0.0), "SideRunState", "side_running"),
+            (Vector2::new(0.0, 1.0), "RunState", "running"),
+            (Vector2::ZERO, "IdleState", "idle"),
+        ];
+        for (input, state, animation) in steps {
+            character.input = input;
+            machine.process(&mut character, 0.016);
+            assert_eq!(
+                machine.current_state_name(),
+                Some(state),
+                "输入 {:?}",
+                input
+            );
+            assert_eq!(machine.active_path()[0], "LocomotionState");
+            assert_eq!(
+                character.animations.last().map(String::as_str),
+                Some(animation)
+            );
+
+            // 物理帧按输入移动，空闲时速度为零
+            machine.physics_process(&mut character, 0.016);
+            assert_eq!(machine.current_state_name(), Some(state));
+            let expected = input * (100.0 * RUN_SPEED_MULTIPLIER) as f32;
+            assert_eq!(character.velocity, expected);
+        }
+    }
+
+    #[test]
+    fn physics_process_alone_also_switches() {
+        let mut machine = StateMachine::new();
+        machine.set_table(player_transition_table());
+        let mut character = MockCharacter::default();
+        let idle = machine
+            .table()
+            .and_then(|table| table.registry().create("idle"));
+        machine.start(&mut character, idle.expect("idle已注册"));
+
+        character.input = Vector2::new(0.0, -1.0);
+        machine.physics_process(&mut character, 0.016);
+        assert_eq!(machine.current_state_name(), Some("BackRunState"));
+        assert_eq!(character.animation_direction, "back");
+    }
+
+    #[test]
+    fn decisions_pick_the_same_states_as_the_table() {
+        let mut machine = StateMachine::new();
+        machine.set_table(player_transition_table());
+        let mut character = MockCharacter::default();
+        assert!(machine.start_named(&mut character, "idle"));
+
+        let inputs = [
+            Vector2::new(0.0, -1.0),
+            Vector2::ZERO,
+            Vector2::new(0.7, 0.7),
+            Vector2::new(-1.0, 0.0),
+            Vector2::ZERO,
+            Vector2::new(0.0, 1.0),
+            Vector2::ZERO,
+        ];
+        for input in inputs {
+            character.input = input;
+            // 与character_snapshot相同的键
+            let mut snapshot = Snapshot::new();
+            snapshot.insert(INPUT_DIRECTION_KEY, SnapshotValue::Vector(input.x, input.y));
+            snapshot.insert(
+                CharacterResource::ANIMATION_DIRECTION.name(),
+                SnapshotValue::Text(character.animation_direction.clone()),
+            );
+            snapshot.set_state_id(machine.current_state_id());
+            let decision = machine.decide(&snapshot);
+
+            machine.process(&mut character, 0.016);
+            assert_eq!(
+                decision.as_deref(),
+                machine.current_state_id(),
+                "输入 {:?}",
+                input
+            );
+        }
+        assert_eq!(machine.current_state_id(), Some("idle"));
+    }
+
+    #[test]
+    fn node_states_stay_until_their_after_rule_fires() {
+        // 与状态节点相同的注册方式：RunState节点命名为Sprint，停留0.5秒后回到idle
+        let mut table = player_transition_table();
+        add_node_state(
+            &mut table,
+            "Sprint",
+            || {
+                Some(Box::new(RunState::new(
+                    "Sprint",
+                    "sprinting",
+                    DirectionType::Side,
+                    2.0,
+                )))
+            },
+            Some(("idle".to_string(), 0.5)),
+        );
+        assert_eq!(table.validate("idle"), Ok(()));
+
+        let mut machine = StateMachine::new();
+        machine.set_table(table);
+        let mut character = MockCharacter::default();
+        assert!(machine.start_named(&mut character, "idle"));
+
+        character.input = Vector2::new(1.0, 0.0);
+        assert!(machine.transition_to(&mut character, "Sprint"));
+        for _ in 0..4 {
+            machine.process(&mut character, 0.1);
+            machine.physics_process(&mut character, 0.1);
+            assert_eq!(machine.current_state_id(), Some("Sprint"));
+        }
+        assert_eq!(character.velocity, Vector2::new(200.0, 0.0));
+
+        machine.process(&mut character, 0.1);
+        assert_eq!(machine.current_state_id(), Some("idle"));
+        // 回到移动状态后由移动规则接管
+        machine.process(&mut character, 0.1);
+        assert_eq!(machine.current_state_id(), Some("side_run"));
+    }
+
+    #[test]
+    fn decisions_do_not_replace_node_states() {
+        let mut table = player_transition_table();
+        add_node_state(
+            &mut table,
+            "Sprint",
+            || {
+                Some(Box::new(RunState::new(
+                    "Sprint",
+                    "sprinting",
+                    DirectionType::Default,
+                    2.0,
+                )))
+            },
+            None,
+        );
+        let mut machine = StateMachine::new();
+        machine.set_table(table);
+        let mut character = MockCharacter::default();
+        assert!(machine.start_named(&mut character, "idle"));
+        assert!(machine.transition_to(&mut character, "Sprint"));
+
+        // Sprint以LocomotionState为父状态，但不是移动状态，决策不替换它
+        let mut snapshot = Snapshot::new();
+        snapshot.insert(INPUT_DIRECTION_KEY, SnapshotValue::Vector(0.0, -1.0));
+        snapshot.set_state_id(machine.current_state_id());
+        assert_eq!(machine.decide(&snapshot), None);
+
+        // 没有状态ID的快照也不做决策
+        snapshot.set_state_id(None);
+        assert_eq!(machine.decide(&snapshot), None);
+
+        snapshot.set_state_id(Some("idle"));
+        assert_eq!(machine.decide(&snapshot).as_deref(), Some("back_run"));
+    }
+
+    #[test]
+    fn player_machine_json_matches_the_transition_table() {
+        let source = include_str!("../../../../godot/characters/player_machine.json");
+        let definition = character_definition_loader::<MockCharacter>()
+            .load(source)
+            .expect("player_machine.json应当有效");
+        let table = player_transition_table::<MockCharacter>();
+
+        assert_eq!(definition.initial, "idle");
+        assert_eq!(definition.table.registry().ids(), table.registry().ids());
+        let rules = |table: &TransitionTable<MockCharacter>| -> Vec<_> {
+            table
+                .rules()
+                .iter()
+                .map(|rule| {
+                    (
+                        rule.from().map(str::to_string),
+                        rule.trigger().name(),
+                        rule.label().map(str::to_string),
+                        rule.to().to_string(),
+                    )
+                })
+                .collect()
+        };
+        assert_eq!(rules(&definition.table), rules(&table));
+
+        // 两张表驱动出相同的状态和动画
+        let mut from_json = StateMachine::new();
+        from_json.set_table(definition.table);
+        let mut from_code = StateMachine::new();
+        from_code.set_table(table);
+        let mut json_character = MockCharacter::default();
+        let mut code_character = MockCharacter::default();
+        assert!(from_json.start_named(&mut json_character, "idle"));
+        assert!(from_code.start_named(&mut code_character, "idle"));
+        for input in [
+            Vector2::new(0.0, -1.0),
+            Vector2::ZERO,
+            Vector2::new(1.0, 0.0),
+            Vector2::ZERO,
+            Vector2::new(0.0, 1.0),
+        ] {
+            json_character.input = input;
+            code_character.input = input;
+            from_json.process(&mut json_character, 0.016);
+            from_code.process(&mut code_character, 0.016);
+            assert_eq!(from_json.current_state_id(), from_code.current_state_id());
+            assert_eq!(json_character.animations, code_character.animations);
+        }
+    }
+
+    locomotion_states! {
+        fn dash_registry {
+            "idle" => IdleState,
+            "dash" => DashState,
+        }
+
+        /// 冲刺0.4秒后回到空闲
+        run DashState {
+            animation: "dash",
+            direction: Side,
+            speed_multiplier: 3.0,
+            callbacks {
+                fn timeout(&self) -> Option<f64> {
+                    Some(0.4)
+                }
+
+                fn on_timeout(&mut self, _ctx: &mut C) -> Transition<C> {
+                    Transition::switch(IdleState::default())
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn generated_states_run_custom_callbacks() {
+        let mut machine = StateMachine::new();
+        machine.set_table(TransitionTable::new(dash_registry()));
+        let mut character = MockCharacter::default();
+        assert!(machine.start_named(&mut character, "dash"));
+        assert_eq!(machine.active_path(), ["LocomotionState", "DashState"]);
+        assert_eq!(character.animations, ["dash"]);
+
+        character.input = Vector2::new(1.0, 0.0);
+        machine.physics_process(&mut character, 0.1);
+        assert_eq!(character.velocity, Vector2::new(300.0, 0.0));
+        machine.process(&mut character, 0.3);
+        assert_eq!(machine.current_state_id(), Some("dash"));
+        machine.process(&mut character, 0.1);
+        assert_eq!(machine.current_state_id(), Some("idle"));
+    }
+}
@@ -1,31 +1,92 @@
-use crate::player::states_impl::idle_state::{BackIdleState, IdleState, SideIdleState};
-use crate::utils::character_state_common::{
-    CharacterResource, CharacterStateCommon, DirectionType, determine_direction_type,
-    get_input_direction,
-};
-use crate::utils::state_machine::{GodotInitialState, GodotState, GodotStateTraits};
-use godot::builtin::Vector2;
-use godot::classes::{CharacterBody2D, INode};
-use godot::obj::{Base, Gd};
-use godot::prelude::{GodotClass, godot_api};
+use crate::fsm::state::{State, Transition};
+use crate::player::states_impl::locomotion_state::LocomotionState;
+use crate::utils::character_state_common::{CharacterContext, CharacterStateCommon, DirectionType};
+use crate::utils::state_machine::GodotInitialState;
+use crate::utils::state_node::StateNode;
+use godot::classes::INode;
+use godot::obj::Base;
+use godot::prelude::{GString, GodotClass, StringName, godot_api};
+
+/// 跑步时的速度倍率
+pub const RUN_SPEED_MULTIPLIER: f64 = 1.5;
+
+/// 奔跑状态
+#[derive(Debug)]
+pub struct RunState {
+    /// 播放的动画，为空时按朝向选择（`running`、`back_running`等）
+    animation_name: String,
+    /// 相对于角色基础速度的倍率
+    speed_multiplier: f64,
+    /// 奔跑的朝向，进入状态时设置为动画朝向
+    direction: DirectionType,
+    /// 由状态节点或定义文件创建时为状态ID
+    name: String,
+}
+
+impl Default for RunState {
+    fn default() -> Self {
+        Self::new("", "", DirectionType::Default, RUN_SPEED_MULTIPLIER)
+    }
+}
+
+impl RunState {
+    /// 以`name`作为状态名称创建，`animation_name`为空时按朝向选择动画
+    pub fn new(
+        name: &str,
+        animation_name: &str,
+        direction: DirectionType,
+        speed_multiplier: f64,
+    ) -> Self {
+        Self {
+            animation_name: animation_name.to_string(),
+            speed_multiplier,
+            direction,
+            name: name.to_string(),
+        }
+    }
+}
 
+/// 奔跑状态节点，作为`PlayerStateMachine`的子节点添加后可以在编辑器中调整参数（例如速度更快的冲刺）
 #[derive(GodotClass, Debug)]
-#[class(base=Node)]
-pub struct RunState;
+#[class(base=Node, rename=RunState)]
+pub struct RunStateNode {
+    /// 播放的动画，为空时按朝向选择
+    #[export]
+    animation_name: GString,
+    /// 相对于角色基础速度的倍率
+    #[export]
+    speed_multiplier: f64,
+    /// 奔跑的朝向
+    #[export]
+    direction: DirectionType,
+    /// 停留`duration`秒后自动切换到的状态ID，为空时不自动切换
+    #[export]
+    next_state: StringName,
+    #[export]
+    duration: f64,
+}
 
 #[godot_api]
-impl INode for RunState {
+impl INode for RunStateNode {
     fn init(_base: Base<Self::Base>) -> Self {
-        RunState
+        Self {
+            animation_name: GString::new(),
+            speed_multiplier: RUN_SPEED_MULTIPLIER,
+            direction: DirectionType::Default,
+            next_state: StringName::default(),
+            duration: 0.0,
+        }
     }
 }
 
 // 实现初始状态标记特性
-impl GodotInitialState for RunState {}
+impl<C: CharacterContext> GodotInitialState<C> for RunState {}
 
 impl CharacterStateCommon for RunState {
     fn get_animation_name(&self, animation_direction: &str) -> String {
-        if animation_direction != "default" {
+        if !self.animation_name.is_empty() {
+            self.animation_name.clone()
+        } else if animation_direction != "default" {
             format!("{}_running", animation_direction)
         } else {
             "running".to_string()
@@ -33,204 +94,48 @@ impl CharacterStateCommon for RunState {
     }
 
     fn get_direction_type(&self) -> DirectionType {
-        DirectionType::Default
+        self.direction
     }
-}
 
-impl GodotState for RunState {
-    type Owner = CharacterBody2D;
-    type Resource = CharacterResource;
-
-    fn init(&self, _owner: &mut Gd<CharacterBody2D>, resource: &mut Gd<CharacterResource>) {
-        // 修改：确保在进入RunState时设置为default朝向
-        resource.bind_mut().set_animation_direction("default");
-        let animation_name = self.get_animation_name("default");
-        resource.bind_mut().play_animation(&animation_name);
-    }
-
-    fn process(
-        &self,
-        _owner: &mut Gd<CharacterBody2D>,
-        _resource: &mut Gd<CharacterResource>,
-        _delta: f64,
-    ) -> Option<Box<dyn GodotStateTraits<Owner = CharacterBody2D, Resource = CharacterResource>>>
-    {
-        let direction = get_input_direction();
-
-        if direction.is_zero_approx() {
-            Some(Box::new(IdleState))
-        } else {
-            // 检查方向是否改变，需要切换到其他运动状态
-            let direction_type = determine_direction_type(direction);
-
-            match direction_type {
-                DirectionType::Back => Some(Box::new(BackRunState)),
-                DirectionType::Side => Some(Box::new(SideRunState)),
-                DirectionType::Default => None, // 保持当前状态
-            }
-        }
-    }
-
-    fn physics_process(
-        &self,
-        owner: &mut Gd<CharacterBody2D>,
-        resource: &mut Gd<CharacterResource>,
-        _delta: f64,
-    ) -> Option<Box<dyn GodotStateTraits<Owner = CharacterBody2D, Resource = CharacterResource>>>
-    {
-        let direction = get_input_direction();
-
-        if direction.is_zero_approx() {
-            // 如果没有输入，立即停止移动
-            owner.set_velocity(Vector2::ZERO);
-            return Some(Box::new(IdleState));
-        }
-
-        // 更新速度
-        let speed = resource.bind().get_speed() * 1.5; // 跑步时速度提升
-        let velocity = direction * speed as f32;
-        owner.set_velocity(velocity);
-
-        None
+    fn get_speed_multiplier(&self) -> f64 {
+        self.speed_multiplier
     }
 }
 
-// 后向奔跑状态
-#[derive(Debug)]
-pub struct BackRunState;
-
-impl CharacterStateCommon for BackRunState {
-    fn get_animation_name(&self, _animation_direction: &str) -> String {
-        "back_running".to_string()
+impl<C: CharacterContext> StateNode<C> for RunStateNode {
+    fn create_state(&self, id: &str) -> Box<dyn State<C>> {
+        Box::new(RunState::new(
+            id,
+            &self.animation_name.to_string(),
+            self.direction,
+            self.speed_multiplier,
+        ))
     }
-}
-
-impl GodotInitialState for BackRunState {}
 
-impl GodotState for BackRunState {
-    type Owner = CharacterBody2D;
-    type Resource = CharacterResource;
-
-    fn init(&self, _owner: &mut Gd<CharacterBody2D>, resource: &mut Gd<CharacterResource>) {
-        // 确保设置为back朝向
-        resource.bind_mut().set_animation_direction("back");
-        let animation_name = self.get_animation_name("back");
-        resource.bind_mut().play_animation(&animation_name);
+    fn next_state(&self) -> Option<(String, f64)> {
+        (!self.next_state.is_empty()).then(|| (self.next_state.to_string(), self.duration))
     }
+}
 
-    fn process(
-        &self,
-        _owner: &mut Gd<CharacterBody2D>,
-        _resource: &mut Gd<CharacterResource>,
-        _delta: f64,
-    ) -> Option<Box<dyn GodotStateTraits<Owner = CharacterBody2D, Resource = CharacterResource>>>
-    {
-        let direction = get_input_direction();
-
-        if direction.is_zero_approx() {
-            Some(Box::new(BackIdleState))
+impl<C: CharacterContext> State<C> for RunState {
+    fn name(&self) -> &str {
+        if self.name.is_empty() {
+            "RunState"
         } else {
-            // 检查方向是否改变，需要切换到其他运动状态
-            let direction_type = determine_direction_type(direction);
-
-            match direction_type {
-                DirectionType::Back => None, // 保持当前状态
-                DirectionType::Side => Some(Box::new(SideRunState)),
-                DirectionType::Default => Some(Box::new(RunState)),
-            }
+            &self.name
         }
     }
 
-    fn physics_process(
-        &self,
-        owner: &mut Gd<CharacterBody2D>,
-        resource: &mut Gd<CharacterResource>,
-        _delta: f64,
-    ) -> Option<Box<dyn GodotStateTraits<Owner = CharacterBody2D, Resource = CharacterResource>>>
-    {
-        let direction = get_input_direction();
-
-        if direction.is_zero_approx() {
-            // 如果没有输入，立即停止移动
-            owner.set_velocity(Vector2::ZERO);
-            return Some(Box::new(BackIdleState));
-        }
-
-        // 更新速度
-        let speed = resource.bind().get_speed() * 1.5; // 跑步时速度提升
-        let velocity = direction * speed as f32;
-        owner.set_velocity(velocity);
-
-        None
+    fn parent(&self) -> Option<Box<dyn State<C>>> {
+        Some(Box::new(LocomotionState))
     }
-}
 
-// 侧向奔跑状态
-#[derive(Debug)]
-pub struct SideRunState;
-
-impl CharacterStateCommon for SideRunState {
-    fn get_animation_name(&self, _animation_direction: &str) -> String {
-        "side_running".to_string()
+    fn init(&mut self, ctx: &mut C) {
+        LocomotionState::enter_run(self, ctx);
     }
-}
-
-impl GodotInitialState for SideRunState {}
-
-impl GodotState for SideRunState {
-    type Owner = CharacterBody2D;
-    type Resource = CharacterResource;
-
-    fn init(&self, _owner: &mut Gd<CharacterBody2D>, resource: &mut Gd<CharacterResource>) {
-        // 确保设置为side朝向
-        resource.bind_mut().set_animation_direction("side");
-        let animation_name = self.get_animation_name("side");
-        resource.bind_mut().play_animation(&animation_name);
-    }
-
-    fn process(
-        &self,
-        _owner: &mut Gd<CharacterBody2D>,
-        _resource: &mut Gd<CharacterResource>,
-        _delta: f64,
-    ) -> Option<Box<dyn GodotStateTraits<Owner = CharacterBody2D, Resource = CharacterResource>>>
-    {
-        let direction = get_input_direction();
-
-        if direction.is_zero_approx() {
-            Some(Box::new(SideIdleState))
-        } else {
-            // 检查方向是否改变，需要切换到其他运动状态
-            let direction_type = determine_direction_type(direction);
-
-            match direction_type {
-                DirectionType::Back => Some(Box::new(BackRunState)),
-                DirectionType::Side => None, // 保持当前状态
-                DirectionType::Default => Some(Box::new(RunState)),
-            }
-        }
-    }
-
-    fn physics_process(
-        &self,
-        owner: &mut Gd<CharacterBody2D>,
-        resource: &mut Gd<CharacterResource>,
-        _delta: f64,
-    ) -> Option<Box<dyn GodotStateTraits<Owner = CharacterBody2D, Resource = CharacterResource>>>
-    {
-        let direction = get_input_direction();
-
-        if direction.is_zero_approx() {
-            // 如果没有输入，立即停止移动
-            owner.set_velocity(Vector2::ZERO);
-            return Some(Box::new(SideIdleState));
-        }
-
-        // 更新速度
-        let speed = resource.bind().get_speed() * 1.5; // 跑步时速度提升
-        let velocity = direction * speed as f32;
-        owner.set_velocity(velocity);
 
-        None
+    fn physics_process(&mut self, ctx: &mut C, _delta: f64) -> Transition<C> {
+        LocomotionState::apply_velocity(self, ctx);
+        Transition::Unhandled
     }
 }
@@ -0,0 +1,3 @@
+pub mod rigid_body_state_machine;
+pub mod state_rigid_body;
+pub mod states_impl;
@@ -0,0 +1,43 @@
+use crate::godot_state_machine;
+use crate::rigid_body::states_impl::brake_state::BrakeStateNode;
+use crate::rigid_body::states_impl::registry::{body_definition_loader, body_transition_table};
+use crate::rigid_body::states_impl::thrust_state::ThrustStateNode;
+use crate::utils::blackboard::Blackboard;
+use crate::utils::machine_core::MachineCore;
+use crate::utils::state_machine::GodotContext;
+use godot::classes::RigidBody2D;
+use godot::prelude::*;
+
+/// 刚体状态机使用的上下文，共享数据保存在黑板上。`integrate_forces`回调期间可以通过`body_state`施加力和冲量
+pub type RigidBodyContext = GodotContext<RigidBody2D, Blackboard>;
+
+/// 刚体状态机的主区域名称
+pub const BODY_REGION: &str = "body";
+
+/// 主区域的初始状态ID
+const INITIAL_STATE: &str = "brake";
+
+godot_state_machine! {
+    /// 刚体状态机，用于物理驱动的道具和载具（例如水面上的船）
+    ///
+    /// 挂在`StateRigidBody2D`下，由它转发`_integrate_forces()`。
+    /// 内置`thrust`（推进）和`brake`（制动）状态，也可以添加`ThrustState`/`BrakeState`子节点或指定定义文件，
+    /// 初始状态由导出属性`initial_state`选择。
+    pub struct RigidBodyStateMachine {
+        owner: RigidBody2D,
+        resource: Blackboard,
+        event: (),
+        setup: rigid_body_machine_core,
+    }
+}
+
+/// 创建刚体状态机：主区域使用刚体的转换表，初始状态为制动
+fn rigid_body_machine_core() -> MachineCore<RigidBody2D, Blackboard> {
+    let mut core = MachineCore::new("RigidBodyStateMachine", BODY_REGION, Blackboard::new_gd());
+    core.set_table(body_transition_table());
+    core.set_initial(INITIAL_STATE);
+    core.set_definition_loader(body_definition_loader());
+    core.add_state_node::<ThrustStateNode>();
+    core.add_state_node::<BrakeStateNode>();
+    core
+}
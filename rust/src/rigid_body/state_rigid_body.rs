@@ -0,0 +1,40 @@
+use crate::rigid_body::rigid_body_state_machine::RigidBodyStateMachine;
+use godot::classes::{IRigidBody2D, PhysicsDirectBodyState2D, RigidBody2D};
+use godot::prelude::*;
+
+/// 由状态机驱动的刚体。`_integrate_forces()`只会在刚体自身上调用，这里把它转发给子节点`RigidBodyStateMachine`
+#[derive(GodotClass)]
+#[class(base=RigidBody2D)]
+pub struct StateRigidBody2D {
+    state_machine: Option<Gd<RigidBodyStateMachine>>,
+    base: Base<RigidBody2D>,
+}
+
+#[godot_api]
+impl IRigidBody2D for StateRigidBody2D {
+    fn init(base: Base<RigidBody2D>) -> Self {
+        Self {
+            state_machine: None,
+            base,
+        }
+    }
+
+    fn ready(&mut self) {
+        // 获取状态机节点
+        if self.base().has_node("RigidBodyStateMachine") {
+            self.state_machine = Some(self.base().get_node_as("RigidBodyStateMachine"));
+        } else {
+            godot_warn!(
+                "RigidBodyStateMachine节点未找到，请确保在StateRigidBody2D节点下添加了RigidBodyStateMachine子节点"
+            );
+        }
+    }
+
+    fn integrate_forces(&mut self, state: Option<Gd<PhysicsDirectBodyState2D>>) {
+        if let Some(state_machine) = &mut self.state_machine
+            && let Some(state) = state
+        {
+            state_machine.bind_mut().handle_integrate_forces(state);
+        }
+    }
+}
@@ -0,0 +1,88 @@
+use crate::fsm::state::State;
+use crate::utils::body_state_common::BodyContext;
+use crate::utils::state_machine::GodotInitialState;
+use crate::utils::state_node::StateNode;
+use godot::classes::INode;
+use godot::obj::Base;
+use godot::prelude::{GodotClass, StringName, godot_api};
+
+/// 制动状态的默认阻尼
+pub const DEFAULT_DAMPING: f64 = 2.0;
+
+/// 制动状态：每个物理步施加与速度相反、大小与速度成正比的力，使刚体逐渐停下
+#[derive(Debug)]
+pub struct BrakeState {
+    /// 每单位速度的制动力
+    damping: f64,
+    /// 由状态节点或定义文件创建时为状态ID
+    name: String,
+}
+
+impl Default for BrakeState {
+    fn default() -> Self {
+        Self::new("", DEFAULT_DAMPING)
+    }
+}
+
+impl BrakeState {
+    /// 以`name`作为状态名称创建
+    pub fn new(name: &str, damping: f64) -> Self {
+        Self {
+            damping,
+            name: name.to_string(),
+        }
+    }
+}
+
+/// 制动状态节点，作为`RigidBodyStateMachine`的子节点添加后可以在编辑器中调整阻尼
+#[derive(GodotClass, Debug)]
+#[class(base=Node, rename=BrakeState)]
+pub struct BrakeStateNode {
+    /// 每单位速度的制动力
+    #[export]
+    damping: f64,
+    /// 停留`duration`秒后自动切换到的状态ID，为空时不自动切换
+    #[export]
+    next_state: StringName,
+    #[export]
+    duration: f64,
+}
+
+#[godot_api]
+impl INode for BrakeStateNode {
+    fn init(_base: Base<Self::Base>) -> Self {
+        Self {
+            damping: DEFAULT_DAMPING,
+            next_state: StringName::default(),
+            duration: 0.0,
+        }
+    }
+}
+
+// 实现初始状态标记特性
+impl<C: BodyContext> GodotInitialState<C> for BrakeState {}
+
+impl<C: BodyContext> StateNode<C> for BrakeStateNode {
+    fn create_state(&self, id: &str) -> Box<dyn State<C>> {
+        Box::new(BrakeState::new(id, self.damping))
+    }
+
+    fn next_state(&self) -> Option<(String, f64)> {
+        (!self.next_state.is_empty()).then(|| (self.next_state.to_string(), self.duration))
+    }
+}
+
+impl<C: BodyContext> State<C> for BrakeState {
+    fn name(&self) -> &str {
+        if self.name.is_empty() {
+            "BrakeState"
+        } else {
+            &self.name
+        }
+    }
+
+    fn integrate_forces(&mut self, ctx: &mut C, _delta: f64) {
+        let force = -ctx.linear_velocity() * self.damping as f32;
+        ctx.apply_central_force(force);
+    }
+}
@@ -0,0 +1,3 @@
+pub mod brake_state;
+pub mod registry;
+pub mod thrust_state;
\ No newline at end of file
@@ -0,0 +1,160 @@
+use crate::fsm::definition::{DefinitionError, DefinitionLoader, Field};
+use crate::fsm::registry::{StateFactory, StateRegistry};
+use crate::fsm::table::TransitionTable;
+use crate::rigid_body::states_impl::brake_state::{BrakeState, DEFAULT_DAMPING};
+use crate::rigid_body::states_impl::thrust_state::{DEFAULT_THRUST, ThrustState};
+use crate::utils::body_state_common::BodyContext;
+use godot::builtin::Vector2;
+
+/// 刚体可以按名称切换到的状态
+pub fn body_state_registry<C: BodyContext>() -> StateRegistry<C> {
+    let mut registry = StateRegistry::new();
+    registry.register_default::<BrakeState>("brake");
+    registry.register_default::<ThrustState>("thrust");
+    registry
+}
+
+/// 刚体的转换表：没有自动切换的规则，两个状态都由脚本按名称进入
+pub fn body_transition_table<C: BodyContext>() -> TransitionTable<C> {
+    let mut table = TransitionTable::new(body_state_registry());
+    table.add_entry("brake").add_entry("thrust");
+    table
+}
+
+/// 刚体定义文件可以使用的状态类型和条件，见`DefinitionLoader`。
+///
+/// 状态类型：
+/// - `thrust`：施加固定的推力，参数`force_x`、`force_y`
+/// - `brake`：施加与速度相反的制动力，参数`damping`
+///
+/// 条件：`slower_than`（速度小于指定值）
+pub fn body_definition_loader<C: BodyContext>() -> DefinitionLoader<C> {
+    let mut loader = DefinitionLoader::new();
+    loader
+        .add_state_kind("thrust", thrust_kind)
+        .add_state_kind("brake", brake_kind)
+        .add_condition("slower_than", |argument| {
+            let speed = argument.as_f64()? as f32;
+            Ok(Box::new(move |ctx: &C| {
+                ctx.linear_velocity().length() < speed
+            }))
+        });
+    loader
+}
+
+fn thrust_kind<C: BodyContext>(
+    id: &str,
+    params: &Field<'_>,
+) -> Result<StateFactory<C>, DefinitionError> {
+    params.expect_keys(&["id", "kind", "force_x", "force_y"])?;
+    let id = id.to_string();
+    let force = Vector2::new(
+        params.f64_or("force_x", DEFAULT_THRUST.x as f64)? as f32,
+        params.f64_or("force_y", DEFAULT_THRUST.y as f64)? as f32,
+    );
+    Ok(Box::new(move || Box::new(ThrustState::new(&id, force))))
+}
+
+fn brake_kind<C: BodyContext>(
+    id: &str,
+    params: &Field<'_>,
+) -> Result<StateFactory<C>, DefinitionError> {
+    params.expect_keys(&["id", "kind", "damping"])?;
+    let id = id.to_string();
+    let damping = params.f64_or("damping", DEFAULT_DAMPING)?;
+    Ok(Box::new(move || Box::new(BrakeState::new(&id, damping))))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fsm::machine::StateMachine;
+    use crate::fsm::state::Context;
+
+    /// 不依赖引擎的刚体：速度由测试设置，记录施加的力
+    #[derive(Debug, Default)]
+    struct MockBody {
+        velocity: Vector2,
+        forces: Vec<Vector2>,
+        time_in_state: f64,
+    }
+
+    impl Context for MockBody {
+        type Input = ();
+        type Event = ();
+
+        fn time_in_state(&self) -> f64 {
+            self.time_in_state
+        }
+
+        fn set_time_in_state(&mut self, seconds: f64) {
+            self.time_in_state = seconds;
+        }
+    }
+
+    impl BodyContext for MockBody {
+        fn linear_velocity(&self) -> Vector2 {
+            self.velocity
+        }
+
+        fn apply_central_force(&mut self, force: Vector2) {
+            self.forces.push(force);
+        }
+    }
+
+    #[test]
+    fn integrate_forces_applies_the_state_force() {
+        let table = body_transition_table();
+        assert_eq!(table.validate("brake"), Ok(()));
+
+        let mut machine = StateMachine::new();
+        machine.set_table(table);
+        let mut body = MockBody::default();
+        assert!(machine.start_named(&mut body, "thrust"));
+        machine.integrate_forces(&mut body, 1.0 / 60.0);
+        assert_eq!(body.forces, [DEFAULT_THRUST]);
+
+        body.forces.clear();
+        body.velocity = Vector2::new(10.0, -4.0);
+        assert!(machine.transition_to(&mut body, "brake"));
+        machine.integrate_forces(&mut body, 1.0 / 60.0);
+        assert_eq!(body.forces, [Vector2::new(-20.0, 8.0)]);
+    }
+
+    #[test]
+    fn definitions_build_body_states_and_rules() {
+        let source = r#"{
+            "initial": "cruise",
+            "states": [
+                { "id": "cruise", "kind": "thrust", "force_x": 50, "force_y": -10 },
+                { "id": "stop", "kind": "brake", "damping": 0.5 }
+            ],
+            "transitions": [
+                { "from": "cruise", "after": 1.0, "to": "stop" },
+                { "from": "stop", "on": "physics_process", "to": "cruise", "when": { "slower_than": 1.0 } }
+            ]
+        }"#;
+        let definition = body_definition_loader().load(source).unwrap();
+        assert_eq!(definition.initial, "cruise");
+
+        let mut machine = StateMachine::new();
+        machine.set_table(definition.table);
+        let mut body = MockBody::default();
+        assert!(machine.start_named(&mut body, "cruise"));
+        machine.integrate_forces(&mut body, 0.5);
+        assert_eq!(body.forces, [Vector2::new(50.0, -10.0)]);
+
+        // 推进1秒后制动，速度降到1以下再次推进
+        body.velocity = Vector2::new(8.0, 0.0);
+        machine.process(&mut body, 1.0);
+        assert_eq!(machine.current_state_id(), Some("stop"));
+        machine.integrate_forces(&mut body, 0.5);
+        assert_eq!(body.forces.last(), Some(&Vector2::new(-4.0, 0.0)));
+        machine.physics_process(&mut body, 0.5);
+        assert_eq!(machine.current_state_id(), Some("stop"));
+
+        body.velocity = Vector2::new(0.5, 0.0);
+        machine.physics_process(&mut body, 0.5);
+        assert_eq!(machine.current_state_id(), Some("cruise"));
+    }
+}
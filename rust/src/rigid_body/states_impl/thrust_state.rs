@@ -0,0 +1,88 @@
+use crate::fsm::state::State;
+use crate::utils::body_state_common::BodyContext;
+use crate::utils::state_machine::GodotInitialState;
+use crate::utils::state_node::StateNode;
+use godot::builtin::Vector2;
+use godot::classes::INode;
+use godot::obj::Base;
+use godot::prelude::{GodotClass, StringName, godot_api};
+
+/// 推进状态的默认推力
+pub const DEFAULT_THRUST: Vector2 = Vector2::new(200.0, 0.0);
+
+/// 推进状态：每个物理步在质心施加固定的推力（例如开动的船）
+#[derive(Debug)]
+pub struct ThrustState {
+    /// 施加的推力（全局坐标）
+    force: Vector2,
+    /// 由状态节点或定义文件创建时为状态ID
+    name: String,
+}
+
+impl Default for ThrustState {
+    fn default() -> Self {
+        Self::new("", DEFAULT_THRUST)
+    }
+}
+
+impl ThrustState {
+    /// 以`name`作为状态名称创建
+    pub fn new(name: &str, force: Vector2) -> Self {
+        Self {
+            force,
+            name: name.to_string(),
+        }
+    }
+}
+
+/// 推进状态节点，作为`RigidBodyStateMachine`的子节点添加后可以在编辑器中调整推力
+#[derive(GodotClass, Debug)]
+#[class(base=Node, rename=ThrustState)]
+pub struct ThrustStateNode {
+    /// 施加的推力（全局坐标）
+    #[export]
+    force: Vector2,
+    /// 停留`duration`秒后自动切换到的状态ID，为空时不自动切换
+    #[export]
+    next_state: StringName,
+    #[export]
+    duration: f64,
+}
+
+#[godot_api]
+impl INode for ThrustStateNode {
+    fn init(_base: Base<Self::Base>) -> Self {
+        Self {
+            force: DEFAULT_THRUST,
+            next_state: StringName::default(),
+            duration: 0.0,
+        }
+    }
+}
+
+// 实现初始状态标记特性
+impl<C: BodyContext> GodotInitialState<C> for ThrustState {}
+
+impl<C: BodyContext> StateNode<C> for ThrustStateNode {
+    fn create_state(&self, id: &str) -> Box<dyn State<C>> {
+        Box::new(ThrustState::new(id, self.force))
+    }
+
+    fn next_state(&self) -> Option<(String, f64)> {
+        (!self.next_state.is_empty()).then(|| (self.next_state.to_string(), self.duration))
+    }
+}
+
+impl<C: BodyContext> State<C> for ThrustState {
+    fn name(&self) -> &str {
+        if self.name.is_empty() {
+            "ThrustState"
+        } else {
+            &self.name
+        }
+    }
+
+    fn integrate_forces(&mut self, ctx: &mut C, _delta: f64) {
+        ctx.apply_central_force(self.force);
+    }
+}
@@ -0,0 +1,144 @@
+use crate::fsm::decision::{Snapshot, SnapshotValue};
+use godot::prelude::*;
+use std::fmt;
+use std::marker::PhantomData;
+
+/// 黑板上的键，`T`为值的类型。通常声明为常量，例如`const HEALTH: BlackboardKey<f64> = BlackboardKey::new("health");`
+pub struct BlackboardKey<T> {
+    name: &'static str,
+    value: PhantomData<fn() -> T>,
+}
+
+impl<T> BlackboardKey<T> {
+    pub const fn new(name: &'static str) -> Self {
+        Self {
+            name,
+            value: PhantomData,
+        }
+    }
+
+    /// 键名，GDScript中用它访问同一个值
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+}
+
+impl<T> Clone for BlackboardKey<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for BlackboardKey<T> {}
+
+impl<T> fmt::Debug for BlackboardKey<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("BlackboardKey").field(&self.name).finish()
+    }
+}
+
+/// 黑板：状态之间共享数据的键值存储。
+///
+/// Rust中通过带类型的`BlackboardKey`读写，GDScript中通过键名和Variant读写。值发生变化时发出`value_changed`信号
+#[derive(GodotClass)]
+#[class(init, base=RefCounted)]
+pub struct Blackboard {
+    values: Dictionary,
+    base: Base<RefCounted>,
+}
+
+#[godot_api]
+impl Blackboard {
+    /// 值被设置为不同的值或被删除时发出，删除时`value`为`null`
+    #[signal]
+    fn value_changed(key: StringName, value: Variant);
+
+    /// 读取值，不存在时返回`default`
+    #[func]
+    pub fn get_value(&self, key: StringName, default: Variant) -> Variant {
+        self.values.get(key).unwrap_or(default)
+    }
+
+    /// 写入值
+    #[func]
+    pub fn set_value(&mut self, key: StringName, value: Variant) {
+        if self.values.get(key.clone()).as_ref() == Some(&value) {
+            return;
+        }
+        self.values.set(key.clone(), value.clone());
+        self.base_mut()
+            .emit_signal("value_changed", &[key.to_variant(), value]);
+    }
+
+    /// 是否存在该键
+    #[func]
+    pub fn has_value(&self, key: StringName) -> bool {
+        self.values.contains_key(key)
+    }
+
+    /// 删除值
+    #[func]
+    pub fn erase_value(&mut self, key: StringName) {
+        if self.values.remove(key.clone()).is_some() {
+            self.base_mut()
+                .emit_signal("value_changed", &[key.to_variant(), Variant::nil()]);
+        }
+    }
+
+    /// 所有键名
+    #[func]
+    pub fn get_keys(&self) -> VariantArray {
+        self.values.keys_array()
+    }
+}
+
+impl Blackboard {
+    /// 读取值，不存在或类型不符时返回`None`
+    pub fn get<T: FromGodot>(&self, key: &BlackboardKey<T>) -> Option<T> {
+        self.values
+            .get(StringName::from(key.name()))
+            .and_then(|value| value.try_to::<T>().ok())
+    }
+
+    /// 读取值，不存在或类型不符时返回`default`
+    pub fn get_or<T: FromGodot>(&self, key: &BlackboardKey<T>, default: T) -> T {
+        self.get(key).unwrap_or(default)
+    }
+
+    /// 写入值，值发生变化时发出`value_changed`
+    pub fn set<T: ToGodot>(&mut self, key: &BlackboardKey<T>, value: T) {
+        self.set_value(StringName::from(key.name()), value.to_variant());
+    }
+
+    /// 是否存在该键
+    pub fn contains<T>(&self, key: &BlackboardKey<T>) -> bool {
+        self.has_value(StringName::from(key.name()))
+    }
+
+    /// 删除值
+    pub fn remove<T>(&mut self, key: &BlackboardKey<T>) {
+        self.erase_value(StringName::from(key.name()));
+    }
+
+    /// 复制布尔值、数字、字符串和Vector2，用于在工作线程中决策。对象等其他类型的值会被忽略
+    pub fn snapshot(&self) -> Snapshot {
+        let mut snapshot = Snapshot::new();
+        for (key, value) in self.values.iter_shared() {
+            let value = match value.get_type() {
+                VariantType::BOOL => SnapshotValue::Bool(value.to()),
+                VariantType::INT => SnapshotValue::Int(value.to()),
+                VariantType::FLOAT => SnapshotValue::Float(value.to()),
+                VariantType::STRING | VariantType::STRING_NAME => {
+                    SnapshotValue::Text(value.to_string())
+                }
+                VariantType::VECTOR2 => {
+                    let vector = value.to::<Vector2>();
+                    SnapshotValue::Vector(vector.x, vector.y)
+                }
+                _ => continue,
+            };
+            snapshot.insert(&key.to_string(), value);
+        }
+        snapshot
+    }
+}
@@ -0,0 +1,31 @@
+use crate::fsm::state::Context;
+use crate::utils::blackboard::Blackboard;
+use crate::utils::state_machine::GodotContext;
+use godot::classes::RigidBody2D;
+use godot::prelude::*;
+
+/// 刚体状态所需的上下文能力，使状态逻辑不依赖具体的Godot节点
+pub trait BodyContext: Context {
+    /// 当前线速度
+    fn linear_velocity(&self) -> Vector2;
+
+    /// 在质心施加力，应在`integrate_forces`回调中调用
+    fn apply_central_force(&mut self, force: Vector2);
+}
+
+// `integrate_forces`回调期间通过物理体的直接状态读写，其他回调中直接访问刚体
+impl<E: 'static> BodyContext for GodotContext<RigidBody2D, Blackboard, E> {
+    fn linear_velocity(&self) -> Vector2 {
+        match &self.body_state {
+            Some(body_state) => body_state.get_linear_velocity(),
+            None => self.owner.get_linear_velocity(),
+        }
+    }
+
+    fn apply_central_force(&mut self, force: Vector2) {
+        match &mut self.body_state {
+            Some(body_state) => body_state.apply_central_force_ex().force(force).done(),
+            None => self.owner.apply_central_force(force),
+        }
+    }
+}
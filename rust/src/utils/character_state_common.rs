@@ -1,4 +1,8 @@
-use godot::classes::AnimationPlayer;
+use crate::fsm::decision::{Snapshot, SnapshotValue};
+use crate::fsm::state::Context;
+use crate::utils::blackboard::{Blackboard, BlackboardKey};
+use crate::utils::state_machine::GodotContext;
+use godot::classes::{AnimationPlayer, CharacterBody2D};
 use godot::prelude::*;
 
 // 获取输入方向
@@ -26,59 +30,80 @@ pub fn determine_direction_type(direction: Vector2) -> DirectionType {
     }
 }
 
-// 资源结构体，用于存储状态机需要的数据
+// 资源结构体，用于存储状态机需要的数据。数据保存在黑板上，下面的getter/setter是常用键的便捷访问
 #[derive(GodotClass)]
 #[class(init, base=RefCounted)]
 pub struct CharacterResource {
-    animation_player: Option<Gd<AnimationPlayer>>,
-    animation_direction: String,
-    last_facing_direction: Vector2,
-    speed: f64,
+    #[init(val = Blackboard::new_gd())]
+    blackboard: Gd<Blackboard>,
     base: Base<RefCounted>,
 }
 
+impl CharacterResource {
+    pub const ANIMATION_PLAYER: BlackboardKey<Gd<AnimationPlayer>> =
+        BlackboardKey::new("animation_player");
+    pub const ANIMATION_DIRECTION: BlackboardKey<String> =
+        BlackboardKey::new("animation_direction");
+    pub const LAST_FACING_DIRECTION: BlackboardKey<Vector2> =
+        BlackboardKey::new("last_facing_direction");
+    pub const SPEED: BlackboardKey<f64> = BlackboardKey::new("speed");
+}
+
 #[godot_api]
 impl CharacterResource {
     pub fn new() -> Gd<Self> {
         Gd::from_init_fn(|base| Self {
-            animation_player: None,
-            animation_direction: "default".to_string(),
-            last_facing_direction: Vector2::new(0.0, 0.0),
-            speed: 50.0,
+            blackboard: Blackboard::new_gd(),
             base,
         })
     }
 
+    // 获取黑板，状态和GDScript可以在上面存放任意共享数据
+    #[func]
+    pub fn get_blackboard(&self) -> Gd<Blackboard> {
+        self.blackboard.clone()
+    }
+
     pub fn set_animation_player(&mut self, animation_player: Gd<AnimationPlayer>) {
-        self.animation_player = Some(animation_player);
+        self.blackboard
+            .bind_mut()
+            .set(&Self::ANIMATION_PLAYER, animation_player);
     }
 
     pub fn get_animation_player(&self) -> Option<Gd<AnimationPlayer>> {
-        self.animation_player.clone()
+        self.blackboard.bind().get(&Self::ANIMATION_PLAYER)
     }
 
     pub fn set_animation_direction(&mut self, direction: &str) {
-        self.animation_direction = direction.to_string();
+        self.blackboard
+            .bind_mut()
+            .set(&Self::ANIMATION_DIRECTION, direction.to_string());
     }
 
-    pub fn get_animation_direction(&self) -> &str {
-        &self.animation_direction
+    pub fn get_animation_direction(&self) -> String {
+        self.blackboard
+            .bind()
+            .get_or(&Self::ANIMATION_DIRECTION, "default".to_string())
     }
 
     pub fn set_last_facing_direction(&mut self, direction: Vector2) {
-        self.last_facing_direction = direction;
+        self.blackboard
+            .bind_mut()
+            .set(&Self::LAST_FACING_DIRECTION, direction);
     }
 
     pub fn get_last_facing_direction(&self) -> Vector2 {
-        self.last_facing_direction
+        self.blackboard
+            .bind()
+            .get_or(&Self::LAST_FACING_DIRECTION, Vector2::ZERO)
     }
 
     pub fn set_speed(&mut self, speed: f64) {
-        self.speed = speed;
+        self.blackboard.bind_mut().set(&Self::SPEED, speed);
     }
 
     pub fn get_speed(&self) -> f64 {
-        self.speed
+        self.blackboard.bind().get_or(&Self::SPEED, 50.0)
     }
 
     // 播放动画
@@ -91,6 +116,67 @@ impl CharacterResource {
     }
 }
 
+/// 角色状态所需的上下文能力，使状态逻辑不依赖具体的Godot节点
+pub trait CharacterContext: Context {
+    /// 当前输入方向（已归一化）
+    fn input_direction(&self) -> Vector2;
+
+    /// 设置角色速度
+    fn set_velocity(&mut self, velocity: Vector2);
+
+    /// 角色基础速度
+    fn speed(&self) -> f64;
+
+    /// 当前动画朝向
+    fn animation_direction(&self) -> String;
+
+    /// 设置动画朝向
+    fn set_animation_direction(&mut self, direction: &str);
+
+    /// 播放动画
+    fn play_animation(&mut self, animation_name: &str);
+}
+
+impl<E: 'static> CharacterContext for GodotContext<CharacterBody2D, CharacterResource, E> {
+    fn input_direction(&self) -> Vector2 {
+        get_input_direction()
+    }
+
+    fn set_velocity(&mut self, velocity: Vector2) {
+        self.owner.set_velocity(velocity);
+    }
+
+    fn speed(&self) -> f64 {
+        self.resource.bind().get_speed()
+    }
+
+    fn animation_direction(&self) -> String {
+        self.resource.bind().get_animation_direction()
+    }
+
+    fn set_animation_direction(&mut self, direction: &str) {
+        self.resource.bind_mut().set_animation_direction(direction);
+    }
+
+    fn play_animation(&mut self, animation_name: &str) {
+        self.resource.bind_mut().play_animation(animation_name);
+    }
+}
+
+/// `character_snapshot`中输入方向的键，其他值使用黑板上的键名
+pub const INPUT_DIRECTION_KEY: &str = "input_direction";
+
+/// 并行决策的快照回调（`MachineCore::set_snapshot`）：复制资源黑板上的值和当前的输入方向
+pub fn character_snapshot<O: GodotClass, E>(ctx: &GodotContext<O, CharacterResource, E>) -> Snapshot
+where
+    GodotContext<O, CharacterResource, E>: CharacterContext,
+{
+    let mut snapshot = ctx.resource.bind().get_blackboard().bind().snapshot();
+    let input = ctx.input_direction();
+    snapshot.insert(INPUT_DIRECTION_KEY, SnapshotValue::Vector(input.x, input.y));
+    snapshot
+}
+
 // 基础状态特性
 pub trait CharacterStateCommon {
     fn get_animation_name(&self, animation_direction: &str) -> String;
@@ -99,12 +185,38 @@ pub trait CharacterStateCommon {
     fn get_direction_type(&self) -> DirectionType {
         DirectionType::Default
     }
+
+    // 获取状态的速度倍率
+    fn get_speed_multiplier(&self) -> f64 {
+        1.0
+    }
 }
 
-// 方向类型枚举
-#[derive(Debug, PartialEq, Clone, Copy)]
+// 方向类型枚举，可以作为状态节点的导出属性
+#[derive(GodotConvert, Var, Export, Debug, PartialEq, Clone, Copy)]
+#[godot(via = i64)]
 pub enum DirectionType {
-    Default,
-    Back,
-    Side,
+    Default = 0,
+    Back = 1,
+    Side = 2,
+}
+
+impl DirectionType {
+    // 对应的动画朝向
+    pub fn animation_direction(&self) -> &'static str {
+        match self {
+            DirectionType::Default => "default",
+            DirectionType::Back => "back",
+            DirectionType::Side => "side",
+        }
+    }
+
+    // 根据动画朝向获取方向类型
+    pub fn from_animation_direction(animation_direction: &str) -> Self {
+        match animation_direction {
+            "back" => DirectionType::Back,
+            "side" => DirectionType::Side,
+            _ => DirectionType::Default,
+        }
+    }
 }
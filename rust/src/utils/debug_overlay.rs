@@ -0,0 +1,157 @@
+use crate::utils::blackboard::Blackboard;
+use godot::classes::{CanvasLayer, ICanvasLayer, InputEvent, InputMap, Label, Node2D};
+use godot::prelude::*;
+
+/// 默认的切换动作，在项目设置的输入映射中绑定按键
+const DEFAULT_TOGGLE_ACTION: &str = "toggle_state_debug";
+
+/// 状态机调试面板：在所有者上方实时显示状态机的信息。
+///
+/// 作为任意状态机节点（`godot_state_machine!`生成的类）的子节点添加，或者通过`machine`指定状态机。
+/// 显示当前状态和停留时间、最近的转换、所有者的速度，以及资源黑板上的值，按`toggle_action`切换显示。
+/// 只在debug构建中注册，release构建中不存在该类，不要把它保存到发布用的场景里。
+#[derive(GodotClass)]
+#[class(base=CanvasLayer)]
+pub struct StateMachineDebugOverlay {
+    /// 要显示的状态机，为空时使用父节点
+    #[export]
+    machine: Option<Gd<Node>>,
+    /// 切换显示的输入动作，动作不存在时无法切换
+    #[export]
+    toggle_action: StringName,
+    /// 显示的最近转换条数
+    #[export]
+    history_size: i32,
+    /// 面板相对于所有者位置的偏移（屏幕像素）
+    #[export]
+    offset: Vector2,
+    label: Option<Gd<Label>>,
+    base: Base<CanvasLayer>,
+}
+
+#[godot_api]
+impl ICanvasLayer for StateMachineDebugOverlay {
+    fn init(base: Base<CanvasLayer>) -> Self {
+        Self {
+            machine: None,
+            toggle_action: StringName::from(DEFAULT_TOGGLE_ACTION),
+            history_size: 5,
+            offset: Vector2::new(-80.0, -140.0),
+            label: None,
+            base,
+        }
+    }
+
+    fn ready(&mut self) {
+        if self.machine.is_none() {
+            self.machine = self.base().get_parent();
+        }
+        let label = Label::new_alloc();
+        self.base_mut().add_child(&label);
+        self.label = Some(label);
+    }
+
+    fn process(&mut self, _delta: f64) {
+        if !self.base().is_visible() {
+            return;
+        }
+        let (Some(machine), Some(mut label)) = (self.machine.clone(), self.label.clone()) else {
+            return;
+        };
+
+        label.set_text(&self.describe(machine.clone()));
+        // 跟随所有者在屏幕上的位置
+        let anchor = machine
+            .get_parent()
+            .and_then(|owner| owner.try_cast::<Node2D>().ok())
+            .map(|owner| owner.get_global_transform_with_canvas().origin)
+            .unwrap_or_default();
+        label.set_position(anchor + self.offset);
+    }
+
+    fn unhandled_input(&mut self, event: Gd<InputEvent>) {
+        if self.toggle_action.is_empty() || !InputMap::singleton().has_action(&self.toggle_action) {
+            return;
+        }
+        if event.is_action_pressed(&self.toggle_action) {
+            let visible = !self.base().is_visible();
+            self.base_mut().set_visible(visible);
+        }
+    }
+}
+
+impl StateMachineDebugOverlay {
+    /// 面板的文本，每行一项
+    fn describe(&self, mut machine: Gd<Node>) -> String {
+        if !machine.has_method("get_current_state") {
+            return format!("{} 不是状态机节点", machine.get_name());
+        }
+
+        let mut lines = vec![format!(
+            "{}: {} ({:.2}s)",
+            machine.get_name(),
+            machine.call("get_current_state", &[]),
+            machine
+                .call("get_time_in_state", &[])
+                .try_to::<f64>()
+                .unwrap_or_default()
+        )];
+
+        if let Some(owner) = machine.get_parent() {
+            // CharacterBody2D的velocity或RigidBody2D的linear_velocity
+            let velocity = ["velocity", "linear_velocity"]
+                .into_iter()
+                .map(|property| owner.get(property))
+                .find(|value| !value.is_nil());
+            if let Some(velocity) = velocity {
+                lines.push(format!("velocity: {}", velocity));
+            }
+        }
+
+        if let Some(blackboard) = Self::blackboard(&mut machine) {
+            let blackboard = blackboard.bind();
+            for key in blackboard.get_keys().iter_shared() {
+                let Ok(key) = key.try_to::<StringName>() else {
+                    continue;
+                };
+                let value = blackboard.get_value(key.clone(), Variant::nil());
+                lines.push(format!("{}: {}", key, value));
+            }
+        }
+
+        let history = machine
+            .call("get_history", &[])
+            .try_to::<Array<Dictionary>>()
+            .unwrap_or_default();
+        let count = usize::try_from(self.history_size).unwrap_or_default();
+        let skip = history.len().saturating_sub(count);
+        for entry in history.iter_shared().skip(skip) {
+            lines.push(format!(
+                "{} -> {} ({})",
+                entry.get_or_nil("from"),
+                entry.get_or_nil("to"),
+                entry.get_or_nil("trigger")
+            ));
+        }
+
+        lines.join("\n")
+    }
+
+    /// 状态机资源上的黑板：资源本身是黑板，或者提供`get_blackboard`（例如`CharacterResource`）
+    fn blackboard(machine: &mut Gd<Node>) -> Option<Gd<Blackboard>> {
+        if !machine.has_method("get_resource") {
+            return None;
+        }
+        let mut resource = machine
+            .call("get_resource", &[])
+            .try_to::<Gd<Object>>()
+            .ok()?;
+        if resource.has_method("get_blackboard") {
+            resource = resource
+                .call("get_blackboard", &[])
+                .try_to::<Gd<Object>>()
+                .ok()?;
+        }
+        resource.try_cast::<Blackboard>().ok()
+    }
+}
@@ -0,0 +1,1067 @@
+use crate::fsm::change::StateChange;
+use crate::fsm::decision::Snapshot;
+use crate::fsm::definition::DefinitionLoader;
+use crate::fsm::export::StateGraph;
+use crate::fsm::history::TransitionHistory;
+use crate::fsm::machine::StateMachine;
+use crate::fsm::region::ParallelMachine;
+use crate::fsm::registry::StateRegistry;
+use crate::fsm::table::TransitionTable;
+use crate::utils::state_machine::{GodotContext, GodotState};
+use crate::utils::state_node::{StateNode, StateNodeKind, register_state_node};
+use godot::classes::{Engine, FileAccess, InputEvent, Node, PhysicsDirectBodyState2D, Time};
+use godot::prelude::*;
+
+/// 转换历史保留的记录数
+const HISTORY_CAPACITY: usize = 64;
+
+/// `ready`时、进入初始状态之前调用的回调，用于从所有者节点准备资源。返回错误时状态机不会启动
+pub type ReadyHook<O, R, E> = fn(&mut GodotContext<O, R, E>) -> Result<(), String>;
+
+/// 在主线程中为并行决策拍摄快照的回调，从上下文（通常是资源的黑板）复制决策需要的数据
+pub type SnapshotHook<O, R, E> = fn(&GodotContext<O, R, E>) -> Snapshot;
+
+/// 状态机节点的通用部分：并行状态机、上下文、转换历史，以及启动时的初始状态。
+///
+/// 与所有者和资源的具体类型无关，`godot_state_machine!`生成的节点类都委托给它。
+/// 不指定区域的操作都作用于主区域（第一个区域）。
+///
+/// 上下文在第一次设置所有者（`attach`/`set_owner`）时创建，在此之前分发的回调都会被忽略，
+/// 切换主区域的状态会被记为初始状态。启动顺序是固定的：
+/// 0. 节点类在`ready`开始时调用`load_definition`加载定义文件（如果指定了），
+///    再调用`register_state_nodes`注册作为子节点添加的状态
+/// 1. 校验转换表
+/// 2. 调用`on_ready`准备资源（AnimationPlayer等），失败时报告错误，状态机不启动
+/// 3. 对初始状态调用`ready`，再调用`init`；所有者就绪前已经设置过状态时，对活动状态调用`ready`
+pub struct MachineCore<O: GodotClass, R: GodotClass, E: 'static = ()> {
+    name: String,
+    machine: ParallelMachine<GodotContext<O, R, E>>,
+    history: TransitionHistory,
+    resource: Gd<R>,
+    context: Option<GodotContext<O, R, E>>,
+    initial: Option<String>,
+    initial_state: Option<Box<GodotState<O, R, E>>>,
+    /// 还没有所有者时通过`transition_to`请求的状态ID，`ready`时优先进入
+    requested: Option<String>,
+    on_ready: Option<ReadyHook<O, R, E>>,
+    snapshot: Option<SnapshotHook<O, R, E>>,
+    state_nodes: Vec<StateNodeKind<GodotContext<O, R, E>>>,
+    definition_loader: Option<DefinitionLoader<GodotContext<O, R, E>>>,
+}
+
+impl<O, R, E> MachineCore<O, R, E>
+where
+    O: GodotClass + Inherits<Node>,
+    R: GodotClass,
+    E: 'static,
+{
+    /// 创建只有主区域`primary`的状态机。`name`用于日志和状态图
+    pub fn new(name: &str, primary: &str, resource: Gd<R>) -> Self {
+        let mut machine = ParallelMachine::new();
+        machine.add_region(primary);
+        Self {
+            name: name.to_string(),
+            machine,
+            history: TransitionHistory::new(HISTORY_CAPACITY),
+            resource,
+            context: None,
+            initial: None,
+            initial_state: None,
+            requested: None,
+            on_ready: None,
+            snapshot: None,
+            state_nodes: Vec::new(),
+            definition_loader: None,
+        }
+    }
+
+    /// 状态机名称
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// 为主区域附加转换表
+    pub fn set_table(&mut self, table: TransitionTable<GodotContext<O, R, E>>) {
+        if let Some(primary) = self.machine.primary_mut() {
+            primary.set_table(table);
+        }
+    }
+
+    /// 设置主区域的初始状态ID，`ready`时若主区域还没有状态则从注册表创建
+    pub fn set_initial(&mut self, id: &str) {
+        self.initial = Some(id.to_string());
+    }
+
+    /// 主区域的初始状态ID
+    pub fn initial(&self) -> Option<&str> {
+        self.initial.as_deref()
+    }
+
+    /// 直接指定主区域的初始状态，优先于初始状态ID
+    pub fn set_initial_state(&mut self, state: Box<GodotState<O, R, E>>) {
+        self.initial_state = Some(state);
+        self.requested = None;
+    }
+
+    /// 设置`ready`时、进入初始状态之前调用的回调，用于从所有者节点准备资源（AnimationPlayer等）
+    pub fn set_on_ready(&mut self, on_ready: ReadyHook<O, R, E>) {
+        self.on_ready = Some(on_ready);
+    }
+
+    /// 设置并行决策的快照回调。设置后，`StateMachineManager`开启并行决策时会让主区域的状态参与决策
+    pub fn set_snapshot(&mut self, snapshot: SnapshotHook<O, R, E>) {
+        self.snapshot = Some(snapshot);
+    }
+
+    /// 为并行决策拍摄快照，没有设置快照回调、所有者尚未就绪、主区域没有状态或有压入的状态时返回`None`
+    /// （与转换表相同，决策不替换压入的状态）
+    pub fn snapshot(&self) -> Option<Snapshot> {
+        let hook = self.snapshot?;
+        let context = self.context.as_ref()?;
+        let primary = self.primary();
+        if !primary.has_state() || primary.depth() > 1 {
+            return None;
+        }
+        let mut snapshot = hook(context);
+        snapshot.set_time_in_state(primary.time_in_state());
+        snapshot.set_state_id(self.current_state_id());
+        Some(snapshot)
+    }
+
+    /// 应用并行决策选择的状态ID，已经处于该状态时不切换
+    pub fn apply_decision(&mut self, id: &str) -> bool {
+        if self.current_state_id() == Some(id) {
+            return false;
+        }
+        self.transition_to(id)
+    }
+
+    /// 允许`T`类型的子节点作为状态，见`register_state_nodes`
+    pub fn add_state_node<T: StateNode<GodotContext<O, R, E>>>(&mut self) {
+        self.state_nodes
+            .push(register_state_node::<GodotContext<O, R, E>, T>);
+    }
+
+    /// 把已允许类型的状态节点以节点名称为ID注册到主区域的转换表，其他节点会被忽略
+    pub fn register_state_nodes(&mut self, nodes: impl IntoIterator<Item = Gd<Node>>) {
+        if self.state_nodes.is_empty() {
+            return;
+        }
+        let Some(table) = self
+            .machine
+            .primary_mut()
+            .and_then(|machine| machine.table_mut())
+        else {
+            godot_warn!("{}没有转换表，无法注册状态节点", self.name);
+            return;
+        };
+        for node in nodes {
+            for register in &self.state_nodes {
+                if register(&node, table) {
+                    break;
+                }
+            }
+        }
+    }
+
+    /// 设置解析定义文件使用的加载器，决定定义中可以使用哪些状态类型和条件
+    pub fn set_definition_loader(&mut self, loader: DefinitionLoader<GodotContext<O, R, E>>) {
+        self.definition_loader = Some(loader);
+    }
+
+    /// 从定义文件（JSON，例如`res://characters/player_machine.json`）构建主区域的转换表和初始状态，
+    /// 替换代码中设置的转换表。文件无法读取或有错误时逐条报告错误（带行号和路径），保留原来的转换表并返回`false`
+    pub fn load_definition(&mut self, path: &str) -> bool {
+        let Some(loader) = &self.definition_loader else {
+            godot_error!("{}没有设置定义加载器，无法加载 {}", self.name, path);
+            return false;
+        };
+        if !FileAccess::file_exists(path) {
+            godot_error!("{}的定义文件 {} 不存在", self.name, path);
+            return false;
+        }
+        let source = FileAccess::get_file_as_string(path).to_string();
+        match loader.load(&source) {
+            Ok(definition) => {
+                self.set_table(definition.table);
+                self.set_initial(&definition.initial);
+                true
+            }
+            Err(errors) => {
+                for error in errors {
+                    godot_error!("{}定义文件错误：{} {}", self.name, path, error);
+                }
+                false
+            }
+        }
+    }
+
+    /// 核心并行状态机
+    pub fn machine(&self) -> &ParallelMachine<GodotContext<O, R, E>> {
+        &self.machine
+    }
+
+    /// 主区域的名称
+    pub fn primary_region(&self) -> &str {
+        self.machine
+            .region_names()
+            .first()
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// 主区域的状态机
+    pub fn primary(&self) -> &StateMachine<GodotContext<O, R, E>> {
+        self.machine.primary().expect("主区域在创建时添加")
+    }
+
+    /// 主区域的转换表
+    pub fn table(&self) -> Option<&TransitionTable<GodotContext<O, R, E>>> {
+        self.primary().table()
+    }
+
+    /// 主区域的转换表（可变）
+    pub fn table_mut(&mut self) -> Option<&mut TransitionTable<GodotContext<O, R, E>>> {
+        self.machine
+            .primary_mut()
+            .and_then(|machine| machine.table_mut())
+    }
+
+    /// 主区域的状态注册表
+    pub fn registry(&self) -> Option<&StateRegistry<GodotContext<O, R, E>>> {
+        self.table().map(|table| table.registry())
+    }
+
+    /// 主区域的状态注册表（可变）
+    pub fn registry_mut(&mut self) -> Option<&mut StateRegistry<GodotContext<O, R, E>>> {
+        self.table_mut().map(|table| table.registry_mut())
+    }
+
+    /// 主区域的状态图，没有转换表时为空图
+    pub fn state_graph(&self) -> StateGraph {
+        match self.table() {
+            Some(table) => StateGraph::from_table(&self.name, table, self.initial()),
+            None => StateGraph {
+                name: self.name.clone(),
+                initial: None,
+                nodes: Vec::new(),
+                edges: Vec::new(),
+            },
+        }
+    }
+
+    /// 状态回调使用的上下文，还没有所有者时为`None`
+    pub fn context(&self) -> Option<&GodotContext<O, R, E>> {
+        self.context.as_ref()
+    }
+
+    /// 状态回调使用的上下文（可变）
+    pub fn context_mut(&mut self) -> Option<&mut GodotContext<O, R, E>> {
+        self.context.as_mut()
+    }
+
+    /// 共享资源
+    pub fn resource(&self) -> &Gd<R> {
+        &self.resource
+    }
+
+    /// 替换共享资源
+    pub fn set_resource(&mut self, resource: Gd<R>) {
+        if let Some(context) = &mut self.context {
+            context.resource = resource.clone();
+        }
+        self.resource = resource;
+    }
+
+    /// 所有者节点
+    pub fn owner(&self) -> Option<&Gd<O>> {
+        self.context.as_ref().map(|context| &context.owner)
+    }
+
+    /// 设置所有者节点，第一次设置时创建上下文
+    pub fn set_owner(&mut self, owner: Gd<O>) {
+        match &mut self.context {
+            Some(context) => context.owner = owner,
+            None => self.context = Some(GodotContext::new(owner, self.resource.clone())),
+        }
+    }
+
+    /// 以状态机节点的父节点作为所有者。父节点不存在或类型不符时返回`false`
+    pub fn attach(&mut self, parent: Option<Gd<Node>>) -> bool {
+        match parent.and_then(|parent| parent.try_cast::<O>().ok()) {
+            Some(owner) => {
+                self.set_owner(owner);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// 转换历史
+    pub fn history(&self) -> &TransitionHistory {
+        &self.history
+    }
+
+    /// 转换历史（可变）
+    pub fn history_mut(&mut self) -> &mut TransitionHistory {
+        &mut self.history
+    }
+
+    /// 最近一次操作引起的状态变化，附带所在区域的名称
+    pub fn changes(&self) -> &[(String, StateChange)] {
+        self.machine.changes()
+    }
+
+    /// 按类型文档中的顺序启动状态机。必须先设置所有者，否则报告错误
+    pub fn ready(&mut self) {
+        let region = self.primary_region().to_string();
+        let Some(context) = &mut self.context else {
+            godot_error!("{}没有找到所有者节点，状态机不会启动", self.name);
+            return;
+        };
+
+        // 1. 校验转换表，问题只报告不中断
+        if let Some(table) = self.machine.primary().and_then(|machine| machine.table())
+            && let Some(initial) = self.initial.as_deref()
+            && let Err(errors) = table.validate(initial)
+        {
+            for error in errors {
+                godot_error!("{}转换表错误：{}", self.name, error);
+            }
+        }
+
+        // 2. 确保在初始化状态前资源已准备好
+        if let Some(on_ready) = self.on_ready
+            && let Err(error) = on_ready(context)
+        {
+            godot_error!("{}无法启动：{}", self.name, error);
+            return;
+        }
+
+        // 3. 状态的ready，然后是初始状态的init
+        if self
+            .machine
+            .primary()
+            .is_some_and(|machine| machine.has_state())
+        {
+            self.machine.ready(context);
+            self.record_history();
+            return;
+        }
+        if let Some(state) = self.initial_state.take() {
+            self.machine.start(context, &region, state);
+        } else if let Some(id) = self.requested.take().or_else(|| self.initial.clone())
+            && !self.machine.start_named(context, &region, &id)
+        {
+            godot_warn!("{}的初始状态 {} 未注册", self.name, id);
+            return;
+        }
+        self.record_history();
+    }
+
+    /// 切换主区域的状态。还没有所有者时，该状态作为初始状态在`ready`时进入
+    pub fn state(&mut self, state: Box<GodotState<O, R, E>>) {
+        if self.context.is_none() {
+            self.set_initial_state(state);
+            return;
+        }
+        let region = self.primary_region().to_string();
+        self.region(&region, state);
+    }
+
+    /// 切换某个并行区域的状态，区域不存在时会被创建
+    pub fn region(&mut self, region: &str, state: Box<GodotState<O, R, E>>) {
+        let Some(context) = &mut self.context else {
+            godot_warn!("{}还没有所有者，忽略区域 {} 的状态切换", self.name, region);
+            return;
+        };
+        self.machine.state(context, region, state);
+        self.record_history();
+    }
+
+    /// 按注册ID切换主区域的状态。还没有所有者时，该ID作为初始状态在`ready`时进入。ID未注册时返回`false`
+    pub fn transition_to(&mut self, id: &str) -> bool {
+        if !self
+            .registry()
+            .is_some_and(|registry| registry.contains(id))
+        {
+            godot_warn!("状态 {} 未注册", id);
+            return false;
+        }
+        let region = self.primary_region().to_string();
+        let Some(context) = &mut self.context else {
+            self.initial_state = None;
+            self.requested = Some(id.to_string());
+            return true;
+        };
+        let switched = self.machine.transition_to(context, &region, id);
+        self.record_history();
+        switched
+    }
+
+    /// 暂停主区域的当前状态并压入一个临时状态
+    pub fn push(&mut self, state: Box<GodotState<O, R, E>>) {
+        let region = self.primary_region().to_string();
+        let Some(context) = &mut self.context else {
+            godot_warn!("{}还没有所有者，忽略压入的状态", self.name);
+            return;
+        };
+        self.machine.push(context, &region, state);
+        self.record_history();
+    }
+
+    /// 按注册ID压入临时状态。ID未注册时返回`false`
+    pub fn push_named(&mut self, id: &str) -> bool {
+        if !self
+            .registry()
+            .is_some_and(|registry| registry.contains(id))
+        {
+            godot_warn!("状态 {} 未注册", id);
+            return false;
+        }
+        let region = self.primary_region().to_string();
+        let Some(context) = &mut self.context else {
+            return false;
+        };
+        let pushed = self.machine.push_named(context, &region, id);
+        self.record_history();
+        pushed
+    }
+
+    /// 弹出主区域栈顶的临时状态
+    pub fn pop(&mut self) -> bool {
+        let region = self.primary_region().to_string();
+        let Some(context) = &mut self.context else {
+            return false;
+        };
+        let popped = self.machine.pop(context, &region);
+        self.record_history();
+        popped
+    }
+
+    /// 分发自定义事件
+    pub fn send_event(&mut self, event: &E) {
+        let Some(context) = &mut self.context else {
+            return;
+        };
+        self.machine.send_event(context, event);
+        self.record_history();
+    }
+
+    /// 分发输入事件
+    pub fn input(&mut self, event: &Gd<InputEvent>) {
+        let Some(context) = &mut self.context else {
+            return;
+        };
+        self.machine.input(context, event);
+        self.record_history();
+    }
+
+    /// 分发更新事件
+    pub fn process(&mut self, delta: f64) {
+        let Some(context) = &mut self.context else {
+            return;
+        };
+        self.machine.process(context, delta);
+        self.record_history();
+    }
+
+    /// 分发物理更新事件
+    pub fn physics_process(&mut self, delta: f64) {
+        let Some(context) = &mut self.context else {
+            return;
+        };
+        self.machine.physics_process(context, delta);
+        self.record_history();
+    }
+
+    /// 分发物理受力事件。回调期间上下文的`body_state`为物理体的直接状态，`delta`为物理步长
+    pub fn integrate_forces(&mut self, body_state: Gd<PhysicsDirectBodyState2D>) {
+        let Some(context) = &mut self.context else {
+            return;
+        };
+        let delta = body_state.get_step() as f64;
+        context.body_state = Some(body_state);
+        self.machine.integrate_forces(context, delta);
+        context.body_state = None;
+        self.record_history();
+    }
+
+    /// 退出所有区域的所有状态
+    pub fn stop(&mut self) {
+        let Some(context) = &mut self.context else {
+            return;
+        };
+        self.machine.stop(context);
+        self.record_history();
+    }
+
+    /// 主区域当前状态的注册ID，见`StateMachine::current_state_id`。未注册的状态返回其类型名
+    pub fn current_state_id(&self) -> Option<&str> {
+        let primary = self.primary();
+        primary
+            .current_state_id()
+            .or_else(|| primary.current_state_name())
+    }
+
+    /// 最近一次操作需要发出的信号（信号名和参数），非主区域的状态名带有区域前缀
+    pub fn pending_signals(&self) -> Vec<(&'static str, Vec<Variant>)> {
+        let primary = self.primary_region();
+        let mut signals = Vec::new();
+        for (region, change) in self.changes() {
+            let name = |state: &str| {
+                if region == primary || state.is_empty() {
+                    GString::from(state)
+                } else {
+                    GString::from(format!("{}/{}", region, state))
+                }
+            };
+
+            if let Some(exited) = change.exited() {
+                signals.push(("state_exited", vec![name(exited).to_variant()]));
+            }
+            if let Some(entered) = change.entered() {
+                signals.push(("state_entered", vec![name(entered).to_variant()]));
+            }
+            let from = name(change.from.as_deref().unwrap_or_default());
+            let to = name(change.to.as_deref().unwrap_or_default());
+            signals.push(("state_changed", vec![from.to_variant(), to.to_variant()]));
+        }
+        signals
+    }
+
+    /// 转换历史，从旧到新。每条记录包含region、from、to、kind、trigger、frame、time
+    pub fn history_array(&self) -> Array<Dictionary> {
+        self.history
+            .entries()
+            .map(|entry| {
+                let mut dictionary = Dictionary::new();
+                dictionary.set("region", entry.region.as_str());
+                dictionary.set("from", entry.from.as_deref().unwrap_or_default());
+                dictionary.set("to", entry.to.as_deref().unwrap_or_default());
+                dictionary.set("kind", format!("{:?}", entry.kind));
+                dictionary.set("trigger", entry.trigger);
+                dictionary.set("frame", entry.frame);
+                dictionary.set("time", entry.time);
+                dictionary
+            })
+            .collect()
+    }
+
+    /// 把转换历史输出到日志
+    pub fn dump_history(&self) {
+        godot_print!("{} 转换历史（{} 条）：", self.name, self.history.len());
+        for entry in self.history.entries() {
+            godot_print!("  {}", entry);
+        }
+    }
+
+    /// 把最近一次操作引起的状态变化记入历史
+    fn record_history(&mut self) {
+        let changes = self.machine.changes();
+        if changes.is_empty() {
+            return;
+        }
+
+        let frame = Engine::singleton().get_process_frames();
+        let time = Time::singleton().get_ticks_msec() as f64 / 1000.0;
+        for (region, change) in changes {
+            self.history.record(region, change, frame, time);
+        }
+    }
+}
+
+impl<O: GodotClass, R: GodotClass, E: 'static> std::fmt::Debug for MachineCore<O, R, E> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MachineCore")
+            .field("name", &self.name)
+            .field("machine", &self.machine)
+            .field("history", &self.history)
+            .field("context", &self.context)
+            .field("initial", &self.initial)
+            .field("initial_state", &self.initial_state)
+            .field("requested", &self.requested)
+            .finish()
+    }
+}
+
+/// 生成一个状态机节点类（`base=Node`），挂在所有者节点下使用。
+///
+/// 所有者、资源和事件类型可以任意指定，`setup`是返回`MachineCore`的函数，
+/// 用于添加区域、转换表、初始状态等。生成的类实现`GodotMachine`、`GodotInitializer`和`ManagedMachine`（场景中有`StateMachineManager`时由它统一更新），
+/// 提供`state_entered`/`state_exited`/`state_changed`信号、可在编辑器中选择的`initial_state`属性和通用的GDScript接口。
+/// 调用处需要引入`godot::prelude::*`。
+///
+/// ```ignore
+/// godot_state_machine! {
+///     /// 门的状态机
+///     pub struct DoorStateMachine {
+///         owner: StaticBody2D,
+///         resource: DoorResource,
+///         event: (),
+///         setup: door_machine_core,
+///     }
+/// }
+/// ```
+#[macro_export]
+macro_rules! godot_state_machine {
+    (
+        $(#[$meta:meta])*
+        pub struct $name:ident {
+            owner: $owner:ty,
+            resource: $resource:ty,
+            event: $event:ty,
+            setup: $setup:path $(,)?
+        }
+    ) => {
+        $(#[$meta])*
+        #[derive(GodotClass, Debug)]
+        #[class(base=Node)]
+        pub struct $name {
+            core: $crate::utils::machine_core::MachineCore<$owner, $resource, $event>,
+            /// 定义文件，指定时在`ready`时替换`setup`中设置的转换表和初始状态
+            #[export(file = "*.json")]
+            definition: GString,
+            /// 初始状态的注册ID，默认为`setup`中设置的ID，在编辑器中可以为每个场景实例单独选择
+            #[export]
+            initial_state: StringName,
+            /// 注册到的管理器，注册后由管理器统一更新
+            manager: Option<Gd<$crate::utils::machine_manager::StateMachineManager>>,
+            base: Base<Node>,
+        }
+
+        impl $crate::utils::state_machine::GodotMachine for $name {
+            type Owner = $owner;
+            type Resource = $resource;
+            type Event = $event;
+
+            fn init(base: Base<Node>) -> Self {
+                let core = $setup();
+                let initial_state = core.initial().map(StringName::from).unwrap_or_default();
+                Self {
+                    core,
+                    definition: GString::new(),
+                    initial_state,
+                    manager: None,
+                    base,
+                }
+            }
+
+            fn state(
+                &mut self,
+                state: Box<$crate::utils::state_machine::GodotState<$owner, $resource, $event>>,
+            ) {
+                self.sync_owner();
+                self.core.state(state);
+                self.flush_changes();
+            }
+
+            fn region(
+                &mut self,
+                region: &str,
+                state: Box<$crate::utils::state_machine::GodotState<$owner, $resource, $event>>,
+            ) {
+                self.sync_owner();
+                self.core.region(region, state);
+                self.flush_changes();
+            }
+
+            fn send_event(&mut self, event: $event) {
+                self.sync_owner();
+                self.core.send_event(&event);
+                self.flush_changes();
+            }
+        }
+
+        impl $crate::utils::state_machine::GodotInitializer for $name {
+            type Owner = $owner;
+            type Resource = $resource;
+            type Event = $event;
+
+            fn new(
+                state: impl $crate::utils::state_machine::GodotInitialState<
+                    $crate::utils::state_machine::GodotContext<$owner, $resource, $event>,
+                >,
+            ) -> Gd<Self> {
+                Gd::from_init_fn(|base| {
+                    let mut machine: Self = $crate::utils::state_machine::GodotMachine::init(base);
+                    machine.core.set_initial_state(Box::new(state));
+                    machine
+                })
+            }
+        }
+
+        #[godot_api]
+        impl INode for $name {
+            fn init(base: Base<Node>) -> Self {
+                $crate::utils::state_machine::GodotMachine::init(base)
+            }
+
+            fn process(&mut self, delta: f64) {
+                let parent = self.base().get_parent();
+                if self.core.attach(parent) {
+                    self.core.process(delta);
+                    self.flush_changes();
+                }
+            }
+
+            fn physics_process(&mut self, delta: f64) {
+                let parent = self.base().get_parent();
+                if self.core.attach(parent) {
+                    self.core.physics_process(delta);
+                    self.flush_changes();
+                }
+            }
+
+            fn ready(&mut self) {
+                if !self.definition.is_empty() {
+                    let setup_initial = self.core.initial().map(StringName::from).unwrap_or_default();
+                    let definition = self.definition.to_string();
+                    // 没有在编辑器中单独选择初始状态时，使用定义文件中的初始状态
+                    if self.core.load_definition(&definition) && self.initial_state == setup_initial {
+                        self.initial_state = self.core.initial().map(StringName::from).unwrap_or_default();
+                    }
+                }
+                let children = self.base().get_children();
+                self.core.register_state_nodes(children.iter_shared());
+                if !self.initial_state.is_empty() {
+                    let initial_state = self.initial_state.to_string();
+                    self.core.set_initial(&initial_state);
+                }
+                self.start();
+            }
+
+            fn enter_tree(&mut self) {
+                // 重新进入场景树（移除后再添加、reparent、对象池复用）时Godot不会再次调用ready，在这里重新启动
+                if self.base().is_node_ready() {
+                    self.start();
+                }
+            }
+
+            fn exit_tree(&mut self) {
+                // 离开场景树时退出当前状态，让状态有机会清理，重新进入场景树时从初始状态重新启动
+                self.core.stop();
+                self.flush_changes();
+                if let Some(mut manager) = self.manager.take()
+                    && manager.is_instance_valid()
+                {
+                    manager.bind_mut().unregister(self.to_gd().upcast());
+                }
+            }
+
+            fn input(&mut self, event: Gd<::godot::classes::InputEvent>) {
+                let parent = self.base().get_parent();
+                if self.core.attach(parent) {
+                    self.core.input(&event);
+                    self.flush_changes();
+                }
+            }
+        }
+
+        #[godot_dyn]
+        impl $crate::utils::machine_manager::ManagedMachine for $name {
+            fn managed_input(&mut self, event: &Gd<::godot::classes::InputEvent>) {
+                self.core.input(event);
+                self.flush_changes();
+            }
+
+            fn managed_process(&mut self, delta: f64) {
+                self.core.process(delta);
+                self.flush_changes();
+            }
+
+            fn managed_physics_process(&mut self, delta: f64) {
+                self.core.physics_process(delta);
+                self.flush_changes();
+            }
+
+            fn decision(
+                &self,
+            ) -> Option<(&dyn $crate::fsm::decision::Decide, $crate::fsm::decision::Snapshot)> {
+                let snapshot = self.core.snapshot()?;
+                Some((self.core.primary(), snapshot))
+            }
+
+            fn apply_decision(&mut self, id: &str) {
+                self.core.apply_decision(id);
+                self.flush_changes();
+            }
+        }
+
+        #[godot_api]
+        impl $name {
+            /// 进入新状态时发出。非主区域的状态名带有区域前缀，例如`action/AimState`
+            #[signal]
+            fn state_entered(name: GString);
+
+            /// 退出状态时发出（被压栈暂停的状态不会发出）
+            #[signal]
+            fn state_exited(name: GString);
+
+            /// 活动状态变化时发出，状态机启动时`from`为空字符串，停止时`to`为空字符串
+            #[signal]
+            fn state_changed(from: GString, to: GString);
+
+            /// 把主区域的状态图导出为Graphviz DOT文本
+            #[func]
+            pub fn export_dot(&self) -> GString {
+                GString::from(self.core.state_graph().to_dot())
+            }
+
+            /// 把主区域的状态图导出为Mermaid文本
+            #[func]
+            pub fn export_mermaid(&self) -> GString {
+                GString::from(self.core.state_graph().to_mermaid())
+            }
+
+            /// 按注册ID切换主区域的状态，例如`transition_to("side_run")`。ID未注册时返回`false`
+            #[func]
+            pub fn transition_to(&mut self, name: StringName) -> bool {
+                self.sync_owner();
+                let switched = self.core.transition_to(&name.to_string());
+                self.flush_changes();
+                switched
+            }
+
+            /// 按注册ID压入临时状态。ID未注册时返回`false`
+            #[func(rename = push_state)]
+            pub fn push_state_named(&mut self, name: StringName) -> bool {
+                self.sync_owner();
+                let pushed = self.core.push_named(&name.to_string());
+                self.flush_changes();
+                pushed
+            }
+
+            /// 弹出栈顶的临时状态，恢复之前的状态
+            #[func]
+            pub fn pop_state(&mut self) -> bool {
+                self.sync_owner();
+                let popped = self.core.pop();
+                self.flush_changes();
+                popped
+            }
+
+            /// 获取主区域当前状态的注册ID，未注册的状态返回其类型名，没有状态时返回空字符串
+            #[func]
+            pub fn get_current_state(&self) -> StringName {
+                self.core
+                    .current_state_id()
+                    .map(StringName::from)
+                    .unwrap_or_default()
+            }
+
+            /// 获取当前状态的停留时间（秒）
+            #[func]
+            pub fn get_time_in_state(&self) -> f64 {
+                self.core.primary().time_in_state()
+            }
+
+            /// 获取状态共享的资源
+            #[func]
+            pub fn get_resource(&self) -> Gd<$resource> {
+                self.core.resource().clone()
+            }
+
+            /// 获取所有可以按名称切换的状态ID
+            #[func]
+            pub fn get_available_states(&self) -> PackedStringArray {
+                self.core
+                    .registry()
+                    .map(|registry| registry.ids())
+                    .unwrap_or_default()
+                    .into_iter()
+                    .map(GString::from)
+                    .collect()
+            }
+
+            /// 获取并行区域当前的叶子状态名称，区域不存在或没有状态时返回空字符串
+            #[func]
+            pub fn get_region_state(&self, region: GString) -> GString {
+                self.core
+                    .machine()
+                    .region(&region.to_string())
+                    .and_then(|machine| machine.current_state_name())
+                    .map(GString::from)
+                    .unwrap_or_default()
+            }
+
+            /// 获取所有并行区域的名称
+            #[func]
+            pub fn get_regions(&self) -> PackedStringArray {
+                self.core
+                    .machine()
+                    .region_names()
+                    .into_iter()
+                    .map(GString::from)
+                    .collect()
+            }
+
+            /// 获取状态栈的深度
+            #[func]
+            pub fn get_stack_depth(&self) -> i64 {
+                self.core.primary().depth() as i64
+            }
+
+            /// 获取状态栈中所有状态的名称，从栈底到栈顶
+            #[func]
+            pub fn get_state_stack(&self) -> PackedStringArray {
+                self.core
+                    .primary()
+                    .stack_names()
+                    .into_iter()
+                    .map(GString::from)
+                    .collect()
+            }
+
+            /// 从GDScript或信号发送自定义事件，例如`send_event("damage_taken", 10.0)`
+            #[func(rename = send_event)]
+            pub fn send_event_variant(&mut self, name: StringName, payload: Variant) {
+                let event = <$event as $crate::utils::state_machine::GodotEvent>::from_variant(
+                    &name.to_string(),
+                    &payload,
+                );
+                $crate::utils::state_machine::GodotMachine::send_event(self, event);
+            }
+
+            /// 获取最近的转换历史，从旧到新。每条记录包含region、from、to、kind、trigger、frame、time
+            #[func]
+            pub fn get_history(&self) -> Array<Dictionary> {
+                self.core.history_array()
+            }
+
+            /// 把转换历史输出到日志
+            #[func]
+            pub fn dump_history(&self) {
+                self.core.dump_history();
+            }
+
+            /// 清空转换历史
+            #[func]
+            pub fn clear_history(&mut self) {
+                self.core.history_mut().clear();
+            }
+        }
+
+        impl $name {
+            /// 获取当前状态机的所有者（父节点），父节点不存在或类型不符时返回`None`
+            pub fn owner(&self) -> Option<Gd<$owner>> {
+                self.base()
+                    .get_parent()
+                    .and_then(|parent| parent.try_cast::<$owner>().ok())
+            }
+
+            /// 获取通用状态机
+            pub fn core(&self) -> &$crate::utils::machine_core::MachineCore<$owner, $resource, $event> {
+                &self.core
+            }
+
+            /// 获取通用状态机（可变）
+            pub fn core_mut(
+                &mut self,
+            ) -> &mut $crate::utils::machine_core::MachineCore<$owner, $resource, $event> {
+                &mut self.core
+            }
+
+            /// 设置资源
+            pub fn set_resource(&mut self, resource: Gd<$resource>) {
+                self.core.set_resource(resource);
+            }
+
+            /// 获取资源
+            pub fn resource(&self) -> &Gd<$resource> {
+                self.core.resource()
+            }
+
+            /// 暂停当前状态并压入一个临时状态（对话、背包、过场等）
+            pub fn push_state(
+                &mut self,
+                state: Box<$crate::utils::state_machine::GodotState<$owner, $resource, $event>>,
+            ) {
+                self.sync_owner();
+                self.core.push(state);
+                self.flush_changes();
+            }
+
+            /// 处理输入事件
+            pub fn handle_input(&mut self, event: Gd<::godot::classes::InputEvent>) {
+                self.sync_owner();
+                self.core.input(&event);
+                self.flush_changes();
+            }
+
+            /// 处理更新事件
+            pub fn handle_process(&mut self, delta: f64) {
+                self.sync_owner();
+                self.core.process(delta);
+                self.flush_changes();
+            }
+
+            /// 处理物理更新事件
+            pub fn handle_physics_process(&mut self, delta: f64) {
+                self.sync_owner();
+                self.core.physics_process(delta);
+                self.flush_changes();
+            }
+
+            /// 处理物理受力事件，由所有者的`_integrate_forces()`转发
+            pub fn handle_integrate_forces(
+                &mut self,
+                body_state: Gd<::godot::classes::PhysicsDirectBodyState2D>,
+            ) {
+                self.sync_owner();
+                self.core.integrate_forces(body_state);
+                self.flush_changes();
+            }
+
+            /// 获取当前状态的层级路径，例如`LocomotionState/RunState`
+            pub fn current_state_path(&self) -> String {
+                self.core.primary().active_path().join("/")
+            }
+
+            /// 获取当前状态的类型名称（用于调试）
+            pub fn current_state_name(&self) -> String {
+                if let Some(state) = self.core.primary().current_state() {
+                    godot_print!("Current State: {:?}", state);
+                    state.name().to_string()
+                } else {
+                    "None".to_string()
+                }
+            }
+
+            /// 以父节点为所有者，从初始状态启动状态机。场景中有管理器时交给它统一更新，此后使用这里缓存的所有者
+            fn start(&mut self) {
+                let parent = self.base().get_parent();
+                if !self.core.attach(parent) {
+                    godot_error!(
+                        "{}必须是{}节点的子节点",
+                        stringify!($name),
+                        stringify!($owner)
+                    );
+                    return;
+                }
+                self.core.ready();
+                self.flush_changes();
+                if let Some(mut manager) =
+                    $crate::utils::machine_manager::StateMachineManager::find(&self.base())
+                {
+                    manager.bind_mut().register(self.to_gd().into_dyn().upcast());
+                    self.manager = Some(manager);
+                }
+            }
+
+            /// 把上下文中的所有者更新为父节点
+            fn sync_owner(&mut self) {
+                if let Some(owner) = self.owner() {
+                    self.core.set_owner(owner);
+                }
+            }
+
+            /// 发出最近一次操作引起的状态变化对应的信号
+            fn flush_changes(&mut self) {
+                for (signal, args) in self.core.pending_signals() {
+                    self.base_mut().emit_signal(signal, &args);
+                }
+            }
+        }
+    };
+}
@@ -0,0 +1,217 @@
+use crate::fsm::decision::{Decide, DecisionPool, Snapshot};
+use godot::classes::{INode, InputEvent, Node};
+use godot::prelude::*;
+
+/// 由`StateMachineManager`统一更新的状态机，`godot_state_machine!`生成的类都实现了它。
+///
+/// 与节点自己的回调不同，这些方法直接使用`ready`时缓存的所有者，不再每帧查找父节点。
+pub trait ManagedMachine {
+    /// 对应`_input()`回调
+    fn managed_input(&mut self, event: &Gd<InputEvent>);
+
+    /// 对应`_process()`回调
+    fn managed_process(&mut self, delta: f64);
+
+    /// 对应`_physics_process()`回调
+    fn managed_physics_process(&mut self, delta: f64);
+
+    /// 并行决策的输入：主区域的状态机和在主线程中拍摄的快照，不参与并行决策时返回`None`
+    fn decision(&self) -> Option<(&dyn Decide, Snapshot)>;
+
+    /// 在主线程中切换到决策选择的状态
+    fn apply_decision(&mut self, id: &str);
+}
+
+/// 状态机管理器：在一次循环中更新所有注册的状态机，减少大量NPC时每个节点各自回调的开销。
+///
+/// 可选节点，放进场景（或设为自动加载）后，状态机在`ready`时自动注册，并关闭自己的
+/// `process`/`physics_process`/`input`回调；离开场景树时注销，恢复自己的回调，重新进入场景树时再次注册。
+/// 同一场景中的节点都先进入场景树再`ready`，因此管理器在场景中的位置不影响注册。
+///
+/// 开启`parallel_decisions`后，每帧`process`之前先进行并行决策：在主线程中为设置了快照回调的状态机拍摄快照，
+/// 在常驻的工作线程池中调用`State::decide`，再回到主线程切换到选择的状态。线程池在第一次决策时创建，
+/// `worker_threads`改变后重新创建。
+///
+/// 更新顺序：管理器的`physics_process`优先级至少为`PHYSICS_PRIORITY`（-1），因此注册的状态机都在默认优先级的
+/// 所有者节点之前更新，与状态机没有注册时自己设置-1的效果相同（例如`Player`在`move_and_slide`之前得到新的速度）。
+/// 状态机之间按注册顺序更新。
+#[derive(GodotClass)]
+#[class(init, base=Node)]
+pub struct StateMachineManager {
+    /// 是否在每帧`process`之前进行并行决策
+    #[export]
+    parallel_decisions: bool,
+    /// 并行决策使用的线程数，0表示使用所有核心
+    #[export]
+    worker_threads: i32,
+    machines: Vec<DynGd<Node, dyn ManagedMachine>>,
+    /// 更新过程中注销的状态机，更新结束后从列表中移除
+    removed: Vec<InstanceId>,
+    ticking: bool,
+    /// 并行决策的工作线程池
+    pool: Option<DecisionPool>,
+    base: Base<Node>,
+}
+
+#[godot_api]
+impl INode for StateMachineManager {
+    fn enter_tree(&mut self) {
+        self.base_mut().add_to_group(Self::GROUP);
+        // 状态机的物理回调由管理器代为调用，管理器需要排在所有者之前；保留编辑器中设置的更小的值
+        if self.base().get_physics_process_priority() > Self::PHYSICS_PRIORITY {
+            self.base_mut()
+                .set_physics_process_priority(Self::PHYSICS_PRIORITY);
+        }
+    }
+
+    fn process(&mut self, delta: f64) {
+        if self.parallel_decisions {
+            self.decide();
+        }
+        self.tick(|machine| machine.managed_process(delta));
+    }
+
+    fn physics_process(&mut self, delta: f64) {
+        self.tick(|machine| machine.managed_physics_process(delta));
+    }
+
+    fn input(&mut self, event: Gd<InputEvent>) {
+        self.tick(|machine| machine.managed_input(&event));
+    }
+}
+
+#[godot_api]
+impl StateMachineManager {
+    /// 注册状态机并关闭它自己的回调，已注册时忽略
+    #[func]
+    pub fn register(&mut self, mut machine: DynGd<Node, dyn ManagedMachine>) {
+        let instance_id = machine.instance_id();
+        if self
+            .machines
+            .iter()
+            .any(|m| m.instance_id_unchecked() == instance_id)
+        {
+            return;
+        }
+        self.removed.retain(|id| *id != instance_id);
+        machine.set_process(false);
+        machine.set_physics_process(false);
+        machine.set_process_input(false);
+        self.machines.push(machine);
+    }
+
+    /// 注销状态机并恢复它自己的回调
+    #[func]
+    pub fn unregister(&mut self, mut machine: Gd<Node>) {
+        let instance_id = machine.instance_id();
+        self.machines
+            .retain(|m| m.instance_id_unchecked() != instance_id);
+        if self.ticking {
+            self.removed.push(instance_id);
+        }
+        machine.set_process(true);
+        machine.set_physics_process(true);
+        machine.set_process_input(true);
+    }
+
+    /// 注册的状态机数量
+    #[func]
+    pub fn get_machine_count(&self) -> i64 {
+        self.machines.len() as i64
+    }
+}
+
+impl StateMachineManager {
+    /// 管理器所在的分组，状态机通过它查找管理器
+    pub const GROUP: &str = "state_machine_managers";
+
+    /// 管理器`physics_process`优先级的上限，比默认优先级（0）的节点先更新
+    pub const PHYSICS_PRIORITY: i32 = -1;
+
+    /// 查找`node`所在场景树中的管理器，没有时返回`None`
+    pub fn find(node: &Node) -> Option<Gd<Self>> {
+        node.get_tree()?
+            .get_first_node_in_group(Self::GROUP)?
+            .try_cast::<Self>()
+            .ok()
+    }
+
+    /// 依次更新所有状态机
+    fn tick(&mut self, mut update: impl FnMut(&mut dyn ManagedMachine)) {
+        self.batch(|machines| {
+            for machine in machines.iter_mut() {
+                // 被其他状态立即释放的状态机
+                if !machine.is_instance_valid() {
+                    continue;
+                }
+                update(&mut *machine.dyn_bind_mut());
+            }
+        });
+    }
+
+    /// 并行决策：拍摄快照和决策时只共享绑定状态机，应用阶段再逐个独占绑定，
+    /// 以免切换状态发出的信号访问其他状态机时发生绑定冲突
+    fn decide(&mut self) {
+        let Some(pool) = self.take_pool() else {
+            return;
+        };
+        self.batch(|machines| {
+            let decisions = {
+                let guards: Vec<_> = machines
+                    .iter()
+                    .map(|machine| machine.is_instance_valid().then(|| machine.dyn_bind()))
+                    .collect();
+                let inputs: Vec<_> = guards
+                    .iter()
+                    .map(|guard| guard.as_ref().and_then(|machine| machine.decision()))
+                    .collect();
+                pool.decide_parallel(&inputs, |input| {
+                    input
+                        .as_ref()
+                        .and_then(|(machine, snapshot)| machine.decide(snapshot))
+                })
+            };
+            for (machine, decision) in machines.iter_mut().zip(decisions) {
+                if let Some(id) = decision
+                    && machine.is_instance_valid()
+                {
+                    machine.dyn_bind_mut().apply_decision(&id);
+                }
+            }
+        });
+        self.pool = Some(pool);
+    }
+
+    /// 取出线程池，线程数与`worker_threads`不一致时重新创建，创建失败时报告错误并返回`None`
+    fn take_pool(&mut self) -> Option<DecisionPool> {
+        let threads = usize::try_from(self.worker_threads).unwrap_or_default();
+        match self.pool.take() {
+            Some(pool) if pool.threads() == threads => Some(pool),
+            _ => match DecisionPool::new(threads) {
+                Ok(pool) => Some(pool),
+                Err(error) => {
+                    godot_error!("无法创建并行决策的线程池：{}", error);
+                    self.parallel_decisions = false;
+                    None
+                }
+            },
+        }
+    }
+
+    /// 对取出的状态机列表执行`run`。状态的回调可能生成或移除状态机，它们会重新绑定管理器进行注册或注销，
+    /// 因此先取出列表，并通过`base_mut`允许重入
+    fn batch(&mut self, run: impl FnOnce(&mut [DynGd<Node, dyn ManagedMachine>])) {
+        let mut machines = std::mem::take(&mut self.machines);
+        self.ticking = true;
+        {
+            let _guard = self.base_mut();
+            run(&mut machines);
+        }
+        self.ticking = false;
+
+        machines.append(&mut self.machines);
+        let removed = std::mem::take(&mut self.removed);
+        machines.retain(|m| m.is_instance_valid() && !removed.contains(&m.instance_id_unchecked()));
+        self.machines = machines;
+    }
+}
@@ -1,2 +1,9 @@
 pub mod state_machine;
-pub mod character_state_common;
\ No newline at end of file
+pub mod blackboard;
+pub mod body_state_common;
+pub mod character_state_common;
+pub mod machine_core;
+pub mod machine_manager;
+pub mod state_node;
+#[cfg(debug_assertions)]
+pub mod debug_overlay;
\ No newline at end of file
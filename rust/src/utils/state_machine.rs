@@ -1,95 +1,120 @@
-use godot::classes::InputEvent;
+use crate::fsm::region::RegionSnapshot;
+use crate::fsm::state::{Context, State};
+use godot::classes::{InputEvent, PhysicsDirectBodyState2D};
 use godot::prelude::*;
+use std::marker::PhantomData;
 
-pub trait GodotStateTraits: GodotState + Sync + Send + std::any::Any + std::fmt::Debug {
-    /// 返回`self` as `&mut dyn Any`
-    fn as_any_mut(&mut self) -> &mut dyn std::any::Any;
+/// Godot上下文：状态回调时可访问的所有者节点与共享资源。
+///
+/// 这是核心状态机（`crate::fsm`）与Godot之间的适配层。`E`是该状态机接受的自定义事件类型。
+#[derive(Clone)]
+pub struct GodotContext<O: GodotClass, R: GodotClass, E = ()> {
+    pub owner: Gd<O>,
+    pub resource: Gd<R>,
+    pub regions: RegionSnapshot,
+    /// 当前状态的停留时间（秒），由状态机在回调前写入
+    pub time_in_state: f64,
+    /// 物理体的直接状态，只在`integrate_forces`回调期间存在（所有者为`RigidBody2D`时）
+    pub body_state: Option<Gd<PhysicsDirectBodyState2D>>,
+    event: PhantomData<fn() -> E>,
 }
 
-/// 状态是一个虚拟基类，允许结构在状态机中用作状态。
-pub trait GodotState: std::fmt::Debug {
-    type Owner: GodotClass + Inherits<Node>;
-    type Resource: GodotClass;
+impl<O: GodotClass, R: GodotClass, E> GodotContext<O, R, E> {
+    pub fn new(owner: Gd<O>, resource: Gd<R>) -> Self {
+        Self {
+            owner,
+            resource,
+            regions: RegionSnapshot::default(),
+            time_in_state: 0.0,
+            body_state: None,
+            event: PhantomData,
+        }
+    }
+}
 
-    /// 虚拟功能。更改活动状态后，由状态机器调用
-    fn init(&self, _owner: &mut Gd<Self::Owner>, _resource: &mut Gd<Self::Resource>) {}
+impl<O: GodotClass, R: GodotClass, E> std::fmt::Debug for GodotContext<O, R, E> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("GodotContext")
+            .field("owner", &self.owner)
+            .field("resource", &self.resource)
+            .field("regions", &self.regions)
+            .field("time_in_state", &self.time_in_state)
+            .field("body_state", &self.body_state)
+            .finish()
+    }
+}
 
-    /// 虚拟函数。对应于`_ready()`回调
-    fn ready(&self, _owner: &mut Gd<Self::Owner>, _resource: &mut Gd<Self::Resource>) {}
+impl<O: GodotClass, R: GodotClass, E: 'static> Context for GodotContext<O, R, E> {
+    type Input = Gd<InputEvent>;
+    type Event = E;
 
-    /// 虚拟函数。对应`_input()`回调
-    fn input(
-        &self,
-        _owner: &Gd<Self::Owner>,
-        _resource: &mut Gd<Self::Resource>,
-        _event: Gd<InputEvent>,
-    ) -> Option<Box<dyn GodotStateTraits<Owner = Self::Owner, Resource = Self::Resource>>> {
-        None
+    fn regions(&self) -> Option<&RegionSnapshot> {
+        Some(&self.regions)
     }
 
-    /// 虚拟函数。对应`_process()`回调
-    fn process(
-        &self,
-        _owner: &mut Gd<Self::Owner>,
-        _resource: &mut Gd<Self::Resource>,
-        _delta: f64,
-    ) -> Option<Box<dyn GodotStateTraits<Owner = Self::Owner, Resource = Self::Resource>>> {
-        None
+    fn regions_mut(&mut self) -> Option<&mut RegionSnapshot> {
+        Some(&mut self.regions)
     }
 
-    /// 虚拟函数。对应`_physics_process()`回调
-    fn physics_process(
-        &self,
-        _owner: &mut Gd<Self::Owner>,
-        _resource: &mut Gd<Self::Resource>,
-        _delta: f64,
-    ) -> Option<Box<dyn GodotStateTraits<Owner = Self::Owner, Resource = Self::Resource>>> {
-        None
+    fn time_in_state(&self) -> f64 {
+        self.time_in_state
     }
 
-    /// 虚拟函数。对应`_integrate_forces()`回调
-    fn integrate_forces(
-        &self,
-        _owner: &Gd<Self::Owner>,
-        _resource: &mut Gd<Self::Resource>,
-        _delta: f64,
-    ) {
+    fn set_time_in_state(&mut self, seconds: f64) {
+        self.time_in_state = seconds;
     }
 }
 
-// 允许状态机以类型安全的方式处理各种不同的状态类型，同时保留在运行时检查和转换状态类型的能力
-impl<T> GodotStateTraits for T
-where
-    T: GodotState + std::any::Any + Sync + Send + std::fmt::Debug,
-{
-    fn as_any_mut(&mut self) -> &mut dyn std::any::Any {
-        self
-    }
-}
+/// 以Godot上下文运行的状态
+pub type GodotState<O, R, E = ()> = dyn State<GodotContext<O, R, E>>;
 
 /// 初始状态是一种自定义标记性状，允许状态被用作Machine中的初始状态。
-pub trait GodotInitialState: GodotStateTraits {}
+pub trait GodotInitialState<C: Context>: State<C> {}
+
+/// 可以从GDScript传入的事件名和参数构造的自定义事件
+pub trait GodotEvent: Sized + 'static {
+    /// 从事件名和参数构造事件
+    fn from_variant(name: &str, payload: &Variant) -> Self;
+}
+
+// 不接受自定义事件的状态机，GDScript发送的事件都作为空事件分发
+impl GodotEvent for () {
+    fn from_variant(_name: &str, _payload: &Variant) -> Self {}
+}
 
 /// Machine提供查询状态机的当前状态所需的方法。
 pub trait GodotMachine: std::fmt::Debug {
     type Owner: GodotClass + Inherits<Node>;
     type Resource: GodotClass;
+    type Event: 'static;
 
     /// 允许您初始化Machine的当前状态。
     fn init(base: Base<Node>) -> Self;
 
     /// 允许您更新Machine的当前状态。
-    fn state(
+    fn state(&mut self, state: Box<GodotState<Self::Owner, Self::Resource, Self::Event>>);
+
+    /// 允许您更新某个并行区域的当前状态，区域不存在时会被创建。
+    fn region(
         &mut self,
-        state: Box<dyn GodotStateTraits<Owner = Self::Owner, Resource = Self::Resource>>,
+        region: &str,
+        state: Box<GodotState<Self::Owner, Self::Resource, Self::Event>>,
     );
+
+    /// 允许您向当前状态发送自定义事件。
+    fn send_event(&mut self, event: Self::Event);
 }
 
 /// 构造并返回一台新状态机
-pub trait GodotInitializer {
+pub trait GodotInitializer: GodotClass {
     type Owner: GodotClass + Inherits<Node>;
     type Resource: GodotClass;
+    type Event: 'static;
 
     /// 新的初始化一台新机器，基于提供的"GodotInitialState"作为输入。
-    fn new(state: impl GodotInitialState<Owner = Self::Owner, Resource = Self::Resource>) -> Self;
+    /// 初始状态在节点进入场景树（`ready`）、能够访问所有者时进入，优先于按名称选择的初始状态。
+    /// 它只用于第一次启动，节点重新进入场景树时使用按名称选择的初始状态。
+    fn new(
+        state: impl GodotInitialState<GodotContext<Self::Owner, Self::Resource, Self::Event>>,
+    ) -> Gd<Self>;
 }
@@ -0,0 +1,70 @@
+use crate::fsm::state::{Context, State};
+use crate::fsm::table::{Rule, TransitionTable, Trigger};
+use godot::obj::Bounds;
+use godot::obj::bounds::DeclUser;
+use godot::prelude::*;
+
+/// 可以作为状态机子节点在编辑器中添加和调整的状态。
+///
+/// 状态机在`ready`时发现这些子节点，以节点名称为ID注册到状态注册表（替换同名的状态）。
+/// 每次进入状态时都按节点当前的导出参数创建新的状态实例，因此调整参数不需要重新编译。
+/// 节点被释放后，切换到该状态会报告警告并保持当前状态。
+pub trait StateNode<C: Context>: GodotClass + Inherits<Node> + Bounds<Declarer = DeclUser> {
+    /// 按导出参数创建状态，`id`为节点名称，应作为状态的`State::name`
+    fn create_state(&self, id: &str) -> Box<dyn State<C>>;
+
+    /// 自动切换：停留指定秒数后切换到的状态ID，不自动切换时返回`None`
+    fn next_state(&self) -> Option<(String, f64)> {
+        None
+    }
+}
+
+/// 把状态节点注册到转换表的函数，`node`不是对应类型时返回`false`
+pub type StateNodeKind<C> = fn(&Gd<Node>, &mut TransitionTable<C>) -> bool;
+
+/// 如果`node`是`T`类型的状态节点，把它注册到转换表，并为自动切换添加规则，见`add_node_state`。
+/// 状态节点通常由脚本按名称进入，因此同时声明为入口状态
+pub fn register_state_node<C: Context, T: StateNode<C>>(
+    node: &Gd<Node>,
+    table: &mut TransitionTable<C>,
+) -> bool {
+    let Ok(state_node) = node.clone().try_cast::<T>() else {
+        return false;
+    };
+
+    let id = node.get_name().to_string();
+    let instance_id = state_node.instance_id();
+    let state_id = id.clone();
+    let next_state = state_node.bind().next_state();
+    add_node_state(
+        table,
+        &id,
+        move || {
+            let Ok(state_node) = Gd::<T>::try_from_instance_id(instance_id) else {
+                godot_warn!("状态节点 {} 已被释放，无法进入该状态", state_id);
+                return None;
+            };
+            Some(state_node.bind().create_state(&state_id))
+        },
+        next_state,
+    );
+    true
+}
+
+/// 以`id`注册状态节点的工厂并声明为入口状态；`next_state`为`Some((to, duration))`时，
+/// 添加停留`duration`秒后切换到`to`的规则。规则只从该状态出发，其他状态的规则不会替换它
+pub fn add_node_state<C, F>(
+    table: &mut TransitionTable<C>,
+    id: &str,
+    factory: F,
+    next_state: Option<(String, f64)>,
+) where
+    C: Context,
+    F: Fn() -> Option<Box<dyn State<C>>> + Send + Sync + 'static,
+{
+    table.registry_mut().register_fallible(id, factory);
+    table.add_entry(id);
+    if let Some((next_state, duration)) = next_state {
+        table.add(Rule::new(Some(id), Trigger::After(duration), &next_state));
+    }
+}